@@ -2,67 +2,456 @@ use std::error;
 
 use ratatui::widgets::ListState;
 
-use crate::{action_display::ActionDisplay, todo::Todo, todo_serializer::JsonSerializer};
+use crate::{
+    action_display::ActionDisplay,
+    command::{self, CommandLine},
+    data_file,
+    due,
+    editor,
+    file_stamp::FileStamp,
+    history::History,
+    hit_map::HitMap,
+    tags,
+    keymap::Keymap,
+    line_editor::LineEditor,
+    search::Search,
+    task::{Priority, Task},
+    task_list::{TaskMove, TaskPath},
+    todo::{Todo, TodoSnapshot},
+    todo_serializer::{self, Backup, FileLock, LockError},
+    view::TodoView,
+};
+
+use time::format_description::FormatItem;
+use time::{macros::format_description, Date};
 
 use std::fmt;
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
 pub type AppResult<T> = std::result::Result<T, Box<dyn error::Error>>;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum AppMode {
     Visual,
+    /// Selecting a range of rows to act on at once.
+    VisualLine,
     Insert,
+    Prompt(PromptKind),
+    Confirm,
+    TagPicker,
+    /// Picking a backup of the todo file to restore.
+    Recover,
+    Command,
 }
 
 impl fmt::Display for AppMode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             AppMode::Visual => write!(f, "Visual"),
+            AppMode::VisualLine => write!(f, "Visual Line"),
             AppMode::Insert => write!(f, "Insert"),
+            AppMode::Prompt(kind) => write!(f, "{}", kind),
+            AppMode::Confirm => write!(f, "Confirm"),
+            AppMode::TagPicker => write!(f, "Tags"),
+            AppMode::Recover => write!(f, "Recover"),
+            AppMode::Command => write!(f, "Command"),
+        }
+    }
+}
+
+/// What the text typed into the footer prompt is for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PromptKind {
+    DueDate,
+    NewList,
+    RenameList,
+    MoveTask,
+    Search,
+}
+
+impl fmt::Display for PromptKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PromptKind::DueDate => write!(f, "Due"),
+            PromptKind::NewList => write!(f, "New list"),
+            PromptKind::RenameList => write!(f, "Rename list"),
+            PromptKind::MoveTask => write!(f, "Move to list"),
+            PromptKind::Search => write!(f, "Search"),
         }
     }
 }
 
+/// Which pane receives keys in visual mode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Focus {
+    List,
+    Calendar,
+}
+
+/// A yes/no question waiting for an answer in [`AppMode::Confirm`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Confirm {
+    /// Tasks being toggled have incomplete subtasks; complete them along
+    /// with them? Holds the ids of all tasks to toggle.
+    CompleteSubtasks(Vec<String>),
+    /// Delete the open list and all of its tasks?
+    DeleteList,
+    /// The todo file changed elsewhere while there are changes here; merge
+    /// them? Holds what the file holds now, and its stamp.
+    MergeFile(TodoSnapshot, Option<FileStamp>),
+    /// Another todo-term, with this process id, has the file open; take it
+    /// over or open it read-only?
+    TakeOverFile(u32),
+}
+
+/// A point in the undo history.
+#[derive(Clone, Debug, PartialEq)]
+struct Snapshot {
+    todo: TodoSnapshot,
+    selected: Option<usize>,
+}
+
 pub struct App {
     running: bool,
     show_help: bool,
     todo: Todo,
+    /// The file the todo is loaded from and saved to.
+    file_path: String,
     action_display: ActionDisplay,
     todo_list_state: ListState,
-    yank_buffer: Option<String>,
+    view: TodoView,
+    focus: Focus,
+    /// Day under the calendar cursor. The calendar shows its month.
+    calendar_date: Date,
+    yank_buffer: Vec<Task>,
+    /// The row a visual line selection started from. The selection spans
+    /// from there to the selected row.
+    selection_anchor: Option<usize>,
     mode: AppMode,
+    /// Where the last frame drew what the mouse can click.
+    hit_map: HitMap,
+    /// The row and time of the last click, to tell double clicks.
+    last_click: Option<(usize, Instant)>,
+    /// The id of the task to open in `$EDITOR` once the terminal is free.
+    editor_request: Option<String>,
+    /// The title of the selected task while it is edited in insert mode.
+    line_editor: LineEditor,
+    /// Text typed into the footer prompt of the current mode.
+    prompt: String,
+    confirm: Option<Confirm>,
+    /// Selects a row of [`get_tag_counts`](App::get_tag_counts) in the tag
+    /// picker.
+    tag_picker_state: ListState,
+    /// The backups listed by `:recover` with their task counts, `None` for
+    /// those that can't be read.
+    backups: Vec<(Backup, Option<usize>)>,
+    backup_picker_state: ListState,
+    search: Search,
+    /// The selected row and search from before the search prompt opened,
+    /// restored if it is cancelled.
+    search_origin: Option<(Option<usize>, Search)>,
+    command_line: CommandLine,
+    keymap: Keymap,
+    /// The keys of a sequence being typed, as shown in the footer.
+    pending_keys: String,
+    /// Set when the todo file exists but could not be read or migrated.
+    /// Saving is refused while this is set so the file is not overwritten.
+    load_error: Option<String>,
+    /// What the todo file held when it was last loaded or saved, the base
+    /// to merge changes made elsewhere with. `None` while it failed to load.
+    disk_snapshot: Option<TodoSnapshot>,
+    disk_stamp: Option<FileStamp>,
+    /// The file changed while a mode that can't be interrupted was open.
+    file_changed: bool,
+    /// Keeps other todo-term processes from saving to the same file.
+    lock: Option<FileLock>,
+    /// The process holding the lock on the file while this one has it open
+    /// read-only.
+    read_only: Option<u32>,
+    history: History<Snapshot>,
 }
 
 impl Default for App {
     fn default() -> Self {
+        Self::with_file(data_file::FILE_NAME)
+    }
+}
+
+/// Loads the todo saved at `path`, or an empty one with the reason it could
+/// not be read.
+fn load_todo(path: &str) -> (Todo, Option<String>) {
+    let mut todo = Todo::with_serializer(todo_serializer::for_path(path));
+    match todo.load() {
+        Ok(()) => (todo, None),
+        Err(e) => (todo, Some(e.to_string())),
+    }
+}
+
+/// The paths that are not below another one of `paths`. Acting on a task
+/// takes its subtasks along, so they are left out.
+fn outermost(paths: Vec<TaskPath>) -> Vec<TaskPath> {
+    let set = paths.iter().cloned().collect::<HashSet<_>>();
+    paths
+        .into_iter()
+        .filter(|path| {
+            let mut parent = path.parent();
+            while let Some(path) = parent {
+                if set.contains(&path) {
+                    return false;
+                }
+                parent = path.parent();
+            }
+            true
+        })
+        .collect()
+}
+
+/// How the time a backup was taken is shown.
+pub const BACKUP_TIME_FORMAT: &[FormatItem] =
+    format_description!("[year]-[month]-[day] [hour]:[minute]:[second] UTC");
+
+/// Two clicks on a row closer together than this are a double click.
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(400);
+
+/// A pasted line without surrounding whitespace and the bullet or checkbox
+/// it may start with.
+fn strip_list_marker(line: &str) -> &str {
+    let line = line.trim();
+    let line = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| line.strip_prefix(marker))
+        .unwrap_or(line);
+    let line = ["[ ] ", "[x] ", "[X] "]
+        .iter()
+        .find_map(|marker| line.strip_prefix(marker))
+        .unwrap_or(line);
+    line.trim()
+}
+
+/// "1 task" or "`n` tasks".
+fn tasks_label(n: usize) -> String {
+    match n {
+        1 => "1 task".to_string(),
+        n => format!("{} tasks", n),
+    }
+}
+
+impl App {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens the todo saved at `file_path`.
+    pub fn with_file(file_path: &str) -> Self {
+        let file_path = file_path.to_string();
         let mut action_display = ActionDisplay::new();
         let mut todo_list_state = ListState::default();
-        let mut todo =
-            Todo::with_serializer(Box::new(JsonSerializer::new("todo.json".to_string())));
-        if let Err(e) = todo.load() {
+        let (todo, load_error) = load_todo(&file_path);
+        if let Some(e) = &load_error {
             action_display.set(&format!("Error loading todo: {}", e));
-        } else {
+        } else if !todo.active_list().is_empty() {
             todo_list_state.select(Some(0));
         }
-        Self {
+        let mut view = TodoView::new();
+        view.refresh(todo.active_list());
+        let mut app = Self {
             running: true,
             show_help: false,
             todo,
+            file_path,
             action_display,
             todo_list_state,
-            yank_buffer: None,
+            view,
+            focus: Focus::List,
+            calendar_date: due::today(),
+            yank_buffer: Vec::new(),
+            selection_anchor: None,
             mode: AppMode::Visual,
+            hit_map: HitMap::new(),
+            last_click: None,
+            editor_request: None,
+            line_editor: LineEditor::new(),
+            prompt: String::new(),
+            confirm: None,
+            tag_picker_state: ListState::default(),
+            backups: Vec::new(),
+            backup_picker_state: ListState::default(),
+            search: Search::new(),
+            search_origin: None,
+            command_line: CommandLine::new(),
+            keymap: Keymap::new(),
+            pending_keys: String::new(),
+            load_error,
+            disk_snapshot: None,
+            disk_stamp: None,
+            file_changed: false,
+            lock: None,
+            read_only: None,
+            history: History::new(),
+        };
+        app.mark_synced();
+        app.lock_file(false);
+        app
+    }
+
+    pub fn tick(&mut self) {
+        self.action_display.tick();
+        if self.file_changed {
+            self.check_file();
         }
     }
-}
 
-impl App {
-    pub fn new() -> Self {
-        Self::default()
+    /// Looks for changes made to the todo file outside of this app, and
+    /// returns whether it can be saved over. Changes are reloaded when there
+    /// are none here, otherwise [`Confirm::MergeFile`] asks what to do.
+    pub fn check_file(&mut self) -> bool {
+        let stamp = FileStamp::of(Path::new(&self.file_path));
+        if stamp == self.disk_stamp {
+            self.file_changed = false;
+            return true;
+        }
+        // a deleted file is written again on the next save
+        if stamp.is_none() {
+            self.disk_stamp = None;
+            self.file_changed = false;
+            return true;
+        }
+        // typing and questions are not interrupted, this waits for them
+        if !matches!(self.mode, AppMode::Visual | AppMode::VisualLine) {
+            self.file_changed = true;
+            return false;
+        }
+        self.file_changed = false;
+
+        let (remote, load_error) = load_todo(&self.file_path);
+        if let Some(e) = load_error {
+            self.action_display.set(&format!(
+                "{} changed but can't be read: {}",
+                self.file_path, e
+            ));
+            return false;
+        }
+        let Some(base) = self.disk_snapshot.clone() else {
+            // the file failed to load before and has been fixed since
+            self.todo = remote;
+            self.load_error = None;
+            self.history = History::new();
+            self.refresh_view();
+            self.mark_synced();
+            self.action_display.set(&format!("Loaded {}", self.file_path));
+            return true;
+        };
+
+        let remote = remote.snapshot();
+        if remote == base {
+            self.disk_stamp = stamp;
+            return true;
+        }
+        if self.todo.snapshot() == base {
+            self.merge_file(remote.clone());
+            self.disk_snapshot = Some(remote);
+            self.disk_stamp = stamp;
+            self.action_display.set(&format!(
+                "Reloaded {}: changed outside todo-term",
+                self.file_path
+            ));
+            return true;
+        }
+
+        self.selection_anchor = None;
+        self.confirm = Some(Confirm::MergeFile(remote, stamp));
+        self.mode = AppMode::Confirm;
+        false
     }
 
-    pub fn tick(&mut self) {
-        self.action_display.tick();
+    /// Takes in the changes made to the file since it was last loaded or
+    /// saved, as one undo step.
+    fn merge_file(&mut self, remote: TodoSnapshot) {
+        let Some(base) = &self.disk_snapshot else {
+            return;
+        };
+        let merged = TodoSnapshot::merge(base, &self.todo.snapshot(), &remote);
+        let selected = self
+            .selected_path()
+            .map(|path| self.todo.active_list().get_task(&path).id().to_string());
+        self.record_history();
+        self.todo.restore(merged);
+        match selected {
+            Some(id) => self.select_task_id(&id),
+            None => self.refresh_view(),
+        }
+        self.action_display
+            .set(&format!("Merged changes to {}", self.file_path));
+    }
+
+    /// Locks the todo file. If another todo-term has it, the file is open
+    /// read-only and [`Confirm::TakeOverFile`] asks whether to take it.
+    fn lock_file(&mut self, take_over: bool) {
+        // a lock on the same file would be removed along with the old one
+        self.lock = None;
+        match self.todo.lock(take_over) {
+            Ok(lock) => {
+                self.lock = lock;
+                if take_over && self.read_only.is_some() {
+                    self.action_display.set(&format!("Took over {}", self.file_path));
+                }
+                self.read_only = None;
+            }
+            Err(LockError::Held(pid)) => {
+                self.read_only = Some(pid);
+                self.confirm = Some(Confirm::TakeOverFile(pid));
+                self.mode = AppMode::Confirm;
+            }
+            // the lock is advisory, so saving goes on without it
+            Err(e) => self
+                .action_display
+                .set(&format!("{}: {}", self.file_path, e)),
+        }
+    }
+
+    /// Takes the lock from the todo-term that has the file, for
+    /// `:takeover`.
+    pub fn take_over_file(&mut self) -> Result<(), String> {
+        if self.read_only.is_none() {
+            return Err(format!("{} is not open anywhere else", self.file_path));
+        }
+        self.lock_file(true);
+        Ok(())
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.is_some()
+    }
+
+    /// Whether this process may write the todo file: it is not read-only
+    /// and still holds the lock. A lock taken over by another process makes
+    /// the file read-only here.
+    fn can_write(&mut self) -> Result<(), String> {
+        if let Some(Err(LockError::Held(pid))) = self.lock.as_ref().map(FileLock::check) {
+            self.lock = None;
+            self.read_only = Some(pid);
+            return Err(format!(
+                "Not saved: pid {} took over {}, it is read-only now",
+                pid, self.file_path
+            ));
+        }
+        match self.read_only {
+            Some(pid) => Err(format!(
+                "Not saved: read-only, pid {} has {} (:takeover)",
+                pid, self.file_path
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Notes what the todo file holds now, after loading or saving it.
+    fn mark_synced(&mut self) {
+        self.disk_snapshot = self.load_error.is_none().then(|| self.todo.snapshot());
+        self.disk_stamp = FileStamp::of(Path::new(&self.file_path));
+        self.file_changed = false;
     }
 
     pub fn quit(&mut self) {
@@ -71,242 +460,1737 @@ impl App {
 
     pub fn add_task_above(&mut self) {
         self.action_display.set("Added task above");
+        self.record_history();
 
-        let new_task_index = {
-            if self.todo.is_empty() {
-                0
-            } else {
-                match self.todo_list_state.selected() {
-                    Some(i) => std::cmp::min(i, self.todo.get_incomplete_tasks().len()),
-                    None => 0,
-                }
-            }
-        };
-        self.todo_list_state.select(Some(new_task_index));
-        self.todo.add_task(new_task_index, "".to_string());
+        self.insert_task(0, self.new_task(""));
+        self.load_line_editor();
     }
 
     pub fn add_task_below(&mut self) {
         self.action_display.set("Added task below");
+        self.record_history();
 
-        let new_task_index = {
-            if self.todo.is_empty() {
-                0
-            } else {
-                match self.todo_list_state.selected() {
-                    Some(i) => std::cmp::min(i + 1, self.todo.get_incomplete_tasks().len()),
-                    None => self.todo.len(),
-                }
-            }
-        };
-        self.todo_list_state.select(Some(new_task_index));
-        self.todo.add_task(new_task_index, "".to_string());
+        self.insert_task(1, self.new_task(""));
+        self.load_line_editor();
     }
 
-    pub fn delete_task(&mut self) {
-        self.action_display.set("Deleted task");
-
-        if self.todo.is_empty() {
+    /// Deletes `count` rows from the selected one down, or the visual line
+    /// selection, as one undo step.
+    pub fn delete_tasks(&mut self, count: usize) {
+        let ids = self.take_target_ids(count);
+        if ids.is_empty() {
             return;
         }
 
-        if let Some(i) = self.todo_list_state.selected() {
-            self.todo.delete_task(i);
-            if i >= self.todo.len() {
-                self.select_last_task();
+        self.record_history();
+        for id in &ids {
+            // a row may have gone with a parent deleted before it
+            if let Some(path) = self.todo.active_list().find(id) {
+                self.todo.active_list_mut().delete_task(&path);
             }
         }
+        self.refresh_view();
+        self.action_display.set(&match ids.len() {
+            1 => "Deleted task".to_string(),
+            n => format!("Deleted {} tasks", n),
+        });
     }
 
+    /// Starts an insert session. Everything changed until
+    /// [`exit_insert_mode`](App::exit_insert_mode) is undone as one step.
     pub fn enter_insert_mode(&mut self) {
+        self.record_history();
         self.mode = AppMode::Insert;
+        self.load_line_editor();
     }
 
     pub fn exit_insert_mode(&mut self) {
+        self.finish_task_edit();
         self.mode = AppMode::Visual;
+        self.history.discard_if_unchanged(&self.snapshot());
     }
 
-    pub fn append_to_task(&mut self, c: char) {
-        if let Some(i) = self.todo_list_state.selected() {
-            let task = if i < self.todo.get_incomplete_tasks().len() {
-                self.todo.get_incomplete_tasks()[i].clone()
-            } else {
-                self.todo.get_complete_tasks()[i - self.todo.get_incomplete_tasks().len()].clone()
-            };
-
-            let new_task = format!("{}{}", task, c);
-
-            self.todo.edit_task(i, new_task);
+    /// Picks up the tags typed into the selected task. If the tag filter no
+    /// longer matches, the task leaves the view.
+    pub fn finish_task_edit(&mut self) {
+        if let Some(path) = self.selected_path() {
+            self.todo.active_list_mut().update_tags(&path);
+            self.select_task(&path);
         }
     }
 
-    pub fn pop_from_task(&mut self) {
-        if let Some(i) = self.todo_list_state.selected() {
-            let task = if i < self.todo.get_incomplete_tasks().len() {
-                self.todo.get_incomplete_tasks()[i].clone()
-            } else {
-                self.todo.get_complete_tasks()[i - self.todo.get_incomplete_tasks().len()].clone()
-            };
+    /// Adds a task below the selected one for every line of pasted text,
+    /// as one undo step. List markers such as `- ` and `- [ ] ` are dropped.
+    pub fn add_tasks_from_text(&mut self, text: &str) {
+        let titles = text
+            .lines()
+            .map(strip_list_marker)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        if titles.is_empty() {
+            return;
+        }
 
-            let new_task = if task.len() > 0 {
-                task[..task.len() - 1].to_string()
-            } else {
-                task
-            };
+        self.record_history();
+        for title in &titles {
+            self.insert_task(1, self.new_task(title));
+        }
+        self.action_display
+            .set(&format!("Pasted {}", tasks_label(titles.len())));
+    }
 
-            self.todo.edit_task(i, new_task);
+    /// Applies an edit to the title of the selected task.
+    pub fn edit_task(&mut self, edit: impl FnOnce(&mut LineEditor)) {
+        if let Some(path) = self.selected_path() {
+            edit(&mut self.line_editor);
+            let title = self.line_editor.get_text().to_string();
+            self.todo.active_list_mut().edit_task(&path, title);
         }
     }
 
     pub fn reset_task(&mut self) {
-        if let Some(i) = self.todo_list_state.selected() {
-            self.todo.edit_task(i, "".to_string());
+        if let Some(path) = self.selected_path() {
+            self.record_history();
+            self.todo.active_list_mut().edit_task(&path, "".to_string());
+            self.load_line_editor();
         }
     }
 
-    pub fn toggle_task(&mut self) {
-        self.action_display.set("Toggled task");
+    /// Asks for the selected task to be opened in `$EDITOR`. The main loop
+    /// picks this up with [`take_editor_request`](App::take_editor_request)
+    /// since the terminal has to be suspended for it.
+    pub fn request_editor(&mut self) {
+        if let Some(path) = self.selected_path() {
+            self.editor_request = Some(self.todo.active_list().get_task(&path).id().to_string());
+        }
+    }
 
-        match self.todo_list_state.selected() {
-            Some(i) => {
-                self.todo.toggle_task(i);
-            }
-            None => {}
+    /// The text to open in the editor, if it was asked for.
+    pub fn take_editor_request(&mut self) -> Option<String> {
+        let id = self.editor_request.as_ref()?;
+        let text = self.todo.active_list().find(id).map(|path| {
+            let task = self.todo.active_list().get_task(&path);
+            editor::to_text(task.title(), task.notes())
+        });
+        if text.is_none() {
+            self.editor_request = None;
         }
+        text
     }
 
-    pub fn yank_task(&mut self) {
-        self.action_display.set("Yanked task");
+    /// Takes in what the editor returned for the requested task.
+    pub fn finish_editor(&mut self, result: Result<String, String>) {
+        let Some(id) = self.editor_request.take() else {
+            return;
+        };
+        let text = match result {
+            Ok(text) => text,
+            Err(e) => {
+                self.action_display.set(&e);
+                return;
+            }
+        };
+        let Some((title, notes)) = editor::from_text(&text) else {
+            self.action_display.set("Empty file, task unchanged");
+            return;
+        };
+        let Some(path) = self.todo.active_list().find(&id) else {
+            return;
+        };
+        let task = self.todo.active_list().get_task(&path);
+        if task.title() == title && task.notes() == notes {
+            self.action_display.set("Task unchanged");
+            return;
+        }
 
-        if let Some(i) = self.todo_list_state.selected() {
-            let task = if i < self.todo.get_incomplete_tasks().len() {
-                self.todo.get_incomplete_tasks()[i].clone()
-            } else {
-                self.todo.get_complete_tasks()[i - self.todo.get_incomplete_tasks().len()].clone()
-            };
+        self.record_history();
+        let list = self.todo.active_list_mut();
+        list.edit_task(&path, title);
+        list.set_notes(&path, notes);
+        list.update_tags(&path);
+        self.select_task(&path);
+        self.action_display.set("Edited task");
+    }
 
-            self.yank_buffer = Some(task);
+    pub fn enter_due_date_prompt(&mut self) {
+        if let Some(path) = self.selected_path() {
+            self.prompt = self
+                .todo
+                .active_list()
+                .get_task(&path)
+                .due()
+                .map(due::format_date)
+                .unwrap_or_default();
+            self.mode = AppMode::Prompt(PromptKind::DueDate);
         }
     }
 
-    pub fn paste_task_above(&mut self) {
-        self.action_display.set("Pasted task above");
+    pub fn push_to_prompt(&mut self, c: char) {
+        self.prompt.push(c);
+        self.update_search();
+    }
 
-        if self.yank_buffer.is_none() {
-            return;
+    pub fn pop_from_prompt(&mut self) {
+        self.prompt.pop();
+        self.update_search();
+    }
+
+    pub fn cancel_prompt(&mut self) {
+        if let Some((selected, search)) = self.search_origin.take() {
+            self.search = search;
+            self.todo_list_state.select(selected);
         }
+        self.prompt.clear();
+        self.selection_anchor = None;
+        self.mode = AppMode::Visual;
+    }
 
-        let new_task_index = {
-            if self.todo.is_empty() {
-                0
-            } else {
-                match self.todo_list_state.selected() {
-                    Some(i) => std::cmp::min(i, self.todo.get_incomplete_tasks().len()),
-                    None => 0,
-                }
+    /// Acts on the typed text. Invalid input keeps the prompt open so it can
+    /// be corrected.
+    pub fn submit_prompt(&mut self) {
+        match self.mode {
+            AppMode::Prompt(PromptKind::DueDate) => self.submit_due_date(),
+            AppMode::Prompt(PromptKind::NewList) => self.submit_new_list(),
+            AppMode::Prompt(PromptKind::RenameList) => self.submit_rename_list(),
+            AppMode::Prompt(PromptKind::MoveTask) => self.submit_move_task(),
+            AppMode::Prompt(PromptKind::Search) => self.submit_search(),
+            _ => {}
+        }
+    }
+
+    fn submit_due_date(&mut self) {
+        let due = match due::parse_due_date(&self.prompt, due::today()) {
+            Ok(due) => due,
+            Err(e) => {
+                self.action_display.set(&e);
+                return;
             }
         };
-        self.todo_list_state.select(Some(new_task_index));
-        self.todo
-            .add_task(new_task_index, self.yank_buffer.clone().unwrap());
+
+        self.cancel_prompt();
+        if let Some(path) = self.selected_path() {
+            self.record_history();
+            self.todo.active_list_mut().set_due(&path, due);
+            self.refresh_view();
+            match due {
+                Some(due) => self
+                    .action_display
+                    .set(&format!("Due {}", due::format_date(due))),
+                None => self.action_display.set("Cleared due date"),
+            }
+        }
     }
 
-    pub fn paste_task_below(&mut self) {
-        self.action_display.set("Pasted task below");
+    pub fn enter_search_prompt(&mut self) {
+        self.search_origin = Some((self.todo_list_state.selected(), self.search.clone()));
+        self.prompt.clear();
+        self.search.clear();
+        self.mode = AppMode::Prompt(PromptKind::Search);
+    }
+
+    /// Switches the search between literal text and regex, also while the
+    /// prompt is open.
+    pub fn toggle_search_regex(&mut self) {
+        self.search.toggle_regex();
+        self.update_search();
+        self.action_display.set(if self.search.is_regex() {
+            "Regex search"
+        } else {
+            "Plain search"
+        });
+    }
 
-        if self.yank_buffer.is_none() {
+    /// Jumps to the first match at or after the row the search started on
+    /// as the pattern is typed.
+    fn update_search(&mut self) {
+        let Some((origin, _)) = self.search_origin else {
+            return;
+        };
+        if self.mode != AppMode::Prompt(PromptKind::Search) {
             return;
         }
 
-        let new_task_index = {
-            if self.todo.is_empty() {
-                0
-            } else {
-                match self.todo_list_state.selected() {
-                    Some(i) => std::cmp::min(i + 1, self.todo.get_incomplete_tasks().len()),
-                    None => self.todo.len(),
-                }
-            }
-        };
-        self.todo_list_state.select(Some(new_task_index));
-        self.todo
-            .add_task(new_task_index, self.yank_buffer.clone().unwrap());
+        self.search.set_pattern(&self.prompt);
+        let start = origin.unwrap_or(0);
+        let row = self.find_match(start, true).or(origin);
+        self.todo_list_state.select(row);
     }
 
-    pub fn navigate_down(&mut self) {
-        if let Some(i) = self.todo_list_state.selected() {
-            let next_index = if i >= self.todo.len() - 1 { 0 } else { i + 1 };
-            self.todo_list_state.select(Some(next_index));
-        };
+    fn submit_search(&mut self) {
+        self.search.set_pattern(&self.prompt);
+        if let Some(e) = self.search.get_error() {
+            self.action_display.set(e);
+            return;
+        }
+
+        self.search_origin = None;
+        self.prompt.clear();
+        self.mode = AppMode::Visual;
+        if self.search.is_empty() {
+            self.action_display.set("Search cleared");
+            return;
+        }
+        match self.todo_list_state.selected() {
+            Some(row) if self.row_matches(row) => self.set_search_action(row, false),
+            _ => self.set_search_action_not_found(),
+        }
     }
 
-    pub fn navigate_up(&mut self) {
-        if let Some(i) = self.todo_list_state.selected() {
-            let next_index = if i == 0 { self.todo.len() - 1 } else { i - 1 };
-            self.todo_list_state.select(Some(next_index));
-        };
+    pub fn search_next(&mut self) {
+        self.jump_to_match(true);
     }
 
-    pub fn navigate_top(&mut self) {
-        self.todo_list_state.select(Some(0));
+    pub fn search_previous(&mut self) {
+        self.jump_to_match(false);
     }
 
-    pub fn navigate_bottom(&mut self) {
-        let next_selected = match self.todo.len() {
-            0 => None,
-            n => Some(n - 1),
+    fn jump_to_match(&mut self, forward: bool) {
+        if self.search.is_empty() {
+            self.action_display.set("No previous search");
+            return;
+        }
+
+        let selected = self.todo_list_state.selected().unwrap_or(0);
+        let len = self.view.len().max(1);
+        let start = if forward {
+            (selected + 1) % len
+        } else {
+            (selected + len - 1) % len
         };
-        self.todo_list_state.select(next_selected);
+        match self.find_match(start, forward) {
+            Some(row) => {
+                let wrapped = if forward { row <= selected } else { row >= selected };
+                self.todo_list_state.select(Some(row));
+                self.set_search_action(row, wrapped);
+            }
+            None => self.set_search_action_not_found(),
+        }
     }
 
-    pub fn toggle_help(&mut self) {
-        self.show_help = !self.show_help;
+    /// The first row from `start` in the given direction whose title
+    /// matches the search, wrapping around the end of the list.
+    fn find_match(&self, start: usize, forward: bool) -> Option<usize> {
+        let len = self.view.len();
+        (0..len)
+            .map(|offset| {
+                if forward {
+                    (start + offset) % len
+                } else {
+                    (start + len - offset) % len
+                }
+            })
+            .find(|row| self.row_matches(*row))
     }
 
-    pub fn is_help_visible(&self) -> bool {
-        self.show_help
+    fn row_matches(&self, row: usize) -> bool {
+        self.view
+            .task_path(row)
+            .is_some_and(|path| self.search.is_match(self.todo.active_list().get_task(path).title()))
     }
 
-    pub fn is_running(&self) -> bool {
-        self.running
+    fn set_search_action(&mut self, row: usize, wrapped: bool) {
+        let matches = (0..self.view.len())
+            .filter(|row| self.row_matches(*row))
+            .collect::<Vec<_>>();
+        let index = matches.iter().position(|r| *r == row).unwrap_or(0) + 1;
+        let mut action = format!("/{} [{}/{}]", self.search.get_pattern(), index, matches.len());
+        if wrapped {
+            action.push_str(" wrapped");
+        }
+        self.action_display.set(&action);
     }
 
-    pub fn is_todo_empty(&self) -> bool {
-        self.todo.is_empty()
+    fn set_search_action_not_found(&mut self) {
+        self.action_display
+            .set(&format!("Pattern not found: {}", self.search.get_pattern()));
     }
 
-    pub fn get_todo_list_state(&mut self) -> &mut ListState {
-        &mut self.todo_list_state
+    pub fn next_list(&mut self) {
+        let next = (self.todo.active_list_index() + 1) % self.todo.get_lists().len();
+        self.open_list(next);
     }
 
-    pub fn get_complete_tasks(&self) -> &Vec<String> {
-        self.todo.get_complete_tasks()
+    pub fn previous_list(&mut self) {
+        let count = self.todo.get_lists().len();
+        let previous = (self.todo.active_list_index() + count - 1) % count;
+        self.open_list(previous);
     }
 
-    pub fn get_incomplete_tasks(&self) -> &Vec<String> {
-        self.todo.get_incomplete_tasks()
+    pub fn enter_new_list_prompt(&mut self) {
+        self.prompt.clear();
+        self.mode = AppMode::Prompt(PromptKind::NewList);
     }
 
-    pub fn get_mode(&self) -> AppMode {
-        self.mode
+    pub fn enter_rename_list_prompt(&mut self) {
+        self.prompt = self.todo.active_list().get_name().to_string();
+        self.mode = AppMode::Prompt(PromptKind::RenameList);
     }
 
-    pub fn get_action(&self) -> &str {
-        self.action_display.get()
+    pub fn enter_move_task_prompt(&mut self) {
+        if self.selected_path().is_some() {
+            self.prompt.clear();
+            self.mode = AppMode::Prompt(PromptKind::MoveTask);
+        }
     }
 
-    pub fn save(&mut self) {
-        if let Err(e) = self.todo.save() {
-            self.action_display
-                .set(&format!("Error saving todo: {}", e));
+    pub fn request_delete_list(&mut self) {
+        if self.todo.get_lists().len() == 1 {
+            self.action_display.set("Can't delete the only list");
+            return;
         }
+        self.confirm = Some(Confirm::DeleteList);
+        self.mode = AppMode::Confirm;
     }
 
-    fn select_last_task(&mut self) {
-        let selected = match self.todo.len() {
-            0 => None,
-            n => Some(n - 1),
+    fn submit_new_list(&mut self) {
+        let name = self.prompt.trim().to_string();
+        if let Err(e) = self.validate_list_name(&name, None) {
+            self.action_display.set(&e);
+            return;
+        }
+
+        self.cancel_prompt();
+        self.record_history();
+        self.todo.add_list(name);
+        self.open_list(self.todo.active_list_index());
+        self.action_display.set("Created list");
+    }
+
+    fn submit_rename_list(&mut self) {
+        let name = self.prompt.trim().to_string();
+        let active = self.todo.active_list_index();
+        if let Err(e) = self.validate_list_name(&name, Some(active)) {
+            self.action_display.set(&e);
+            return;
+        }
+
+        self.cancel_prompt();
+        self.record_history();
+        self.todo.active_list_mut().set_name(name);
+        self.action_display.set("Renamed list");
+    }
+
+    fn submit_move_task(&mut self) {
+        let target = match self.resolve_list(self.prompt.trim()) {
+            Ok(target) => target,
+            Err(e) => {
+                self.action_display.set(&e);
+                return;
+            }
         };
-        self.todo_list_state.select(selected);
+
+        let ids = self.take_target_ids(1);
+        self.cancel_prompt();
+        if target == self.todo.active_list_index() || ids.is_empty() {
+            return;
+        }
+
+        self.record_history();
+        let list = self.todo.active_list();
+        let ids = outermost(ids.iter().filter_map(|id| list.find(id)).collect())
+            .iter()
+            .map(|path| list.get_task(path).id().to_string())
+            .collect::<Vec<_>>();
+        for id in &ids {
+            let path = self.todo.active_list().find(id).expect("selected task exists");
+            let task = self.todo.active_list_mut().delete_task(&path);
+            self.todo.get_list_mut(target).push_task(task);
+        }
+        self.refresh_view();
+        let name = self.todo.get_lists()[target].get_name().to_string();
+        self.action_display
+            .set(&format!("Moved {} to {}", tasks_label(ids.len()), name));
+    }
+
+    /// Finds a list by its full name or a prefix only it starts with.
+    fn resolve_list(&self, name: &str) -> Result<usize, String> {
+        if let Some(index) = self.todo.find_list(name) {
+            return Ok(index);
+        }
+
+        let name = name.to_lowercase();
+        let matches = self
+            .todo
+            .get_lists()
+            .iter()
+            .enumerate()
+            .filter(|(_, list)| list.get_name().to_lowercase().starts_with(&name))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        match matches.as_slice() {
+            [index] => Ok(*index),
+            [] => Err(format!("No list named {}", name)),
+            _ => Err(format!("More than one list starts with {}", name)),
+        }
+    }
+
+    fn validate_list_name(&self, name: &str, renaming: Option<usize>) -> Result<(), String> {
+        if name.is_empty() {
+            return Err("List name can't be empty".to_string());
+        }
+        match self.todo.find_list(name) {
+            Some(index) if Some(index) != renaming => {
+                Err(format!("A list named {} already exists", name))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn open_list(&mut self, index: usize) {
+        self.todo.set_active_list(index);
+        self.view.refresh(self.todo.active_list());
+        self.todo_list_state.select(None);
+        self.navigate_top();
+    }
+
+    /// Toggles `count` rows from the selected one down, or the visual line
+    /// selection. If any of them is being completed with subtasks left
+    /// open, asks first whether to complete those too.
+    pub fn toggle_tasks(&mut self, count: usize) {
+        let ids = self.take_target_ids(count);
+        if ids.is_empty() {
+            return;
+        }
+
+        let list = self.todo.active_list();
+        let asks = ids.iter().any(|id| {
+            let task = list.get_task(&list.find(id).expect("selected task exists"));
+            !task.is_complete() && task.has_incomplete_subtasks()
+        });
+        if asks {
+            self.confirm = Some(Confirm::CompleteSubtasks(ids));
+            self.mode = AppMode::Confirm;
+            return;
+        }
+
+        self.record_history();
+        self.toggle_task_ids(&ids, false);
+    }
+
+    pub fn answer_confirm(&mut self, yes: bool) {
+        self.mode = AppMode::Visual;
+        match self.confirm.take() {
+            Some(Confirm::CompleteSubtasks(ids)) => {
+                self.record_history();
+                self.toggle_task_ids(&ids, yes);
+            }
+            Some(Confirm::DeleteList) if yes => {
+                self.record_history();
+                self.todo.delete_active_list();
+                self.open_list(self.todo.active_list_index());
+                self.action_display.set("Deleted list");
+            }
+            Some(Confirm::DeleteList) => {}
+            Some(Confirm::MergeFile(remote, stamp)) => {
+                if yes {
+                    self.merge_file(remote.clone());
+                } else {
+                    self.action_display
+                        .set(&format!("Keeping your version of {}", self.file_path));
+                }
+                // the next save writes over what the file holds now
+                self.disk_snapshot = Some(remote);
+                self.disk_stamp = stamp;
+            }
+            Some(Confirm::TakeOverFile(_)) if yes => self.lock_file(true),
+            Some(Confirm::TakeOverFile(pid)) => self.action_display.set(&format!(
+                "Opened {} read-only, pid {} has it",
+                self.file_path, pid
+            )),
+            None => {}
+        }
+    }
+
+    pub fn cancel_confirm(&mut self) {
+        self.mode = AppMode::Visual;
+        self.confirm = None;
+    }
+
+    /// Indents the selected task up to `levels` times, as one undo step.
+    pub fn indent_task_by(&mut self, levels: usize) {
+        if let Some(path) = self.selected_path() {
+            self.record_history();
+            let id = self.todo.active_list().get_task(&path).id().to_string();
+            let mut path = path;
+            let mut indented = 0;
+            while indented < levels && self.todo.active_list_mut().indent_task(&path) {
+                indented += 1;
+                path = self.todo.active_list().find(&id).expect("indented task exists");
+            }
+            if indented > 0 {
+                self.select_task_id(&id);
+                self.action_display.set("Indented task");
+            } else {
+                self.history.discard_if_unchanged(&self.snapshot());
+            }
+        }
+    }
+
+    /// Outdents the selected task up to `levels` times, as one undo step.
+    pub fn outdent_task_by(&mut self, levels: usize) {
+        if let Some(path) = self.selected_path() {
+            self.record_history();
+            let id = self.todo.active_list().get_task(&path).id().to_string();
+            let mut path = path;
+            let mut outdented = 0;
+            while outdented < levels && self.todo.active_list_mut().outdent_task(&path) {
+                outdented += 1;
+                path = self.todo.active_list().find(&id).expect("outdented task exists");
+            }
+            if outdented > 0 {
+                self.select_task_id(&id);
+                self.action_display.set("Outdented task");
+            } else {
+                self.history.discard_if_unchanged(&self.snapshot());
+            }
+        }
+    }
+
+    /// Moves the selected task `count` places among its siblings, or to the
+    /// top or bottom of them, keeping it selected. See
+    /// [`TaskList::move_task`](crate::task_list::TaskList::move_task) for
+    /// how far it can go.
+    pub fn move_task(&mut self, to: TaskMove, count: usize) {
+        let Some(path) = self.selected_path() else {
+            return;
+        };
+        self.record_history();
+        let id = self.todo.active_list().get_task(&path).id().to_string();
+        let mut moved = 0;
+        while moved < count {
+            let path = self.todo.active_list().find(&id).expect("moved task exists");
+            if !self.todo.active_list_mut().move_task(&path, to) {
+                break;
+            }
+            moved += 1;
+        }
+
+        if moved == 0 {
+            self.history.discard_if_unchanged(&self.snapshot());
+            self.action_display.set(match to {
+                TaskMove::Up | TaskMove::Top => "Can't move task further up",
+                TaskMove::Down | TaskMove::Bottom => "Can't move task further down",
+            });
+            return;
+        }
+        self.select_task_id(&id);
+        self.action_display.set(match to {
+            TaskMove::Up => "Moved task up",
+            TaskMove::Down => "Moved task down",
+            TaskMove::Top => "Moved task to top",
+            TaskMove::Bottom => "Moved task to bottom",
+        });
+    }
+
+    pub fn toggle_fold(&mut self) {
+        if let Some(path) = self.selected_path() {
+            if self.todo.active_list().get_task(&path).subtasks().is_empty() {
+                return;
+            }
+            self.todo.active_list_mut().toggle_fold(&path);
+            self.select_task(&path);
+        }
+    }
+
+    pub fn set_all_folded(&mut self, folded: bool) {
+        // keep the selection on the top-level task that contains it
+        let top = self.selected_path().map(|path| TaskPath::root(path.top()));
+        self.todo.active_list_mut().set_all_folded(folded);
+        match top {
+            Some(path) => self.select_task(&path),
+            None => self.refresh_view(),
+        }
+        self.action_display
+            .set(if folded { "Folded all" } else { "Unfolded all" });
+    }
+
+    /// Raises the priority of the selected task, or of every task in the
+    /// visual line selection.
+    pub fn raise_priority(&mut self) {
+        self.change_priority(true);
+    }
+
+    pub fn lower_priority(&mut self) {
+        self.change_priority(false);
+    }
+
+    fn change_priority(&mut self, raise: bool) {
+        let Some(selected) = self.selected_path() else {
+            return;
+        };
+        let selected = self.todo.active_list().get_task(&selected).id().to_string();
+        let ids = self.take_target_ids(1);
+
+        self.record_history();
+        for id in &ids {
+            let path = self.todo.active_list().find(id).expect("selected task exists");
+            if raise {
+                self.todo.active_list_mut().raise_priority(&path);
+            } else {
+                self.todo.active_list_mut().lower_priority(&path);
+            }
+        }
+        self.select_task_id(&selected);
+        match ids.len() {
+            1 => self.set_priority_action(&selected),
+            n => self.action_display.set(&format!(
+                "{} priority of {} tasks",
+                if raise { "Raised" } else { "Lowered" },
+                n
+            )),
+        }
+    }
+
+    pub fn toggle_sort_by_priority(&mut self) {
+        let id = self
+            .selected_path()
+            .map(|path| self.todo.active_list().get_task(&path).id().to_string());
+        let sort_by_priority = !self.todo.active_list().is_sorted_by_priority();
+        self.record_history();
+        self.todo.active_list_mut().set_sort_by_priority(sort_by_priority);
+        match id {
+            Some(id) => self.select_task_id(&id),
+            None => self.refresh_view(),
+        }
+        self.action_display.set(if sort_by_priority {
+            "Sorting by priority"
+        } else {
+            "Manual order"
+        });
+    }
+
+    /// Yanks `count` rows from the selected one down, or the visual line
+    /// selection. Subtasks of a yanked task come along with it.
+    pub fn yank_tasks(&mut self, count: usize) {
+        let ids = self.take_target_ids(count);
+        if ids.is_empty() {
+            return;
+        }
+
+        let list = self.todo.active_list();
+        self.yank_buffer = outermost(ids.iter().filter_map(|id| list.find(id)).collect())
+            .iter()
+            .map(|path| list.get_task(path).clone())
+            .collect();
+        self.action_display.set(&match self.yank_buffer.len() {
+            1 => "Yanked task".to_string(),
+            n => format!("Yanked {} tasks", n),
+        });
+    }
+
+    /// Pastes the yanked tasks `count` times above the selected one. In
+    /// visual line mode they replace the selection instead.
+    pub fn paste_tasks_above(&mut self, count: usize) {
+        self.paste_tasks(0, count);
+    }
+
+    /// Pastes the yanked tasks `count` times below the selected one. In
+    /// visual line mode they replace the selection instead.
+    pub fn paste_tasks_below(&mut self, count: usize) {
+        self.paste_tasks(1, count);
+    }
+
+    fn paste_tasks(&mut self, offset: usize, count: usize) {
+        if self.yank_buffer.is_empty() {
+            self.exit_visual_line();
+            self.action_display.set("Nothing to paste");
+            return;
+        }
+        if self.mode == AppMode::VisualLine {
+            self.replace_selection();
+            return;
+        }
+
+        self.record_history();
+
+        let tasks = self.yank_buffer.clone();
+        for (i, task) in std::iter::repeat_n(&tasks, count).flatten().enumerate() {
+            // the first task goes above or below, the rest follow it
+            self.insert_task(if i == 0 { offset } else { 1 }, task.duplicate());
+        }
+        let pasted = tasks.len() * count;
+        let place = if offset == 0 { "above" } else { "below" };
+        self.action_display.set(&match pasted {
+            1 => format!("Pasted task {}", place),
+            n => format!("Pasted {} tasks {}", n, place),
+        });
+    }
+
+    /// Puts the yanked tasks where the visual line selection is.
+    fn replace_selection(&mut self) {
+        let ids = self.take_target_ids(1);
+        let list = self.todo.active_list();
+        let paths = outermost(ids.iter().filter_map(|id| list.find(id)).collect());
+        let Some(first) = paths.first().cloned() else {
+            return;
+        };
+        let ids = paths
+            .iter()
+            .map(|path| list.get_task(path).id().to_string())
+            .collect::<Vec<_>>();
+
+        self.record_history();
+        for id in &ids {
+            let path = self.todo.active_list().find(id).expect("selected task exists");
+            self.todo.active_list_mut().delete_task(&path);
+        }
+
+        // tasks before the first selected one are untouched, so its place
+        // is still where the pasted tasks go
+        let tasks = self.yank_buffer.iter().map(Task::duplicate).collect::<Vec<_>>();
+        let first_id = tasks[0].id().to_string();
+        for (i, task) in tasks.into_iter().enumerate() {
+            let incomplete_len = self.todo.active_list().get_incomplete_tasks().len();
+            let path = if first.depth() > 0 {
+                first.sibling(first.index() + i)
+            } else {
+                TaskPath::root(std::cmp::min(first.index() + i, incomplete_len))
+            };
+            self.todo.active_list_mut().add_task(&path, task);
+        }
+        self.select_task_id(&first_id);
+        self.action_display
+            .set(&format!("Replaced {} with {}", tasks_label(ids.len()), tasks_label(self.yank_buffer.len())));
+    }
+
+    pub fn undo(&mut self) {
+        match self.history.undo(self.snapshot()) {
+            Some(snapshot) => {
+                self.restore(snapshot);
+                self.action_display.set("Undid change");
+            }
+            None => self.action_display.set("Already at oldest change"),
+        }
+    }
+
+    pub fn redo(&mut self) {
+        match self.history.redo(self.snapshot()) {
+            Some(snapshot) => {
+                self.restore(snapshot);
+                self.action_display.set("Redid change");
+            }
+            None => self.action_display.set("Already at newest change"),
+        }
+    }
+
+    pub fn navigate_down(&mut self) {
+        if let Some(i) = self.todo_list_state.selected() {
+            let next_index = if i + 1 >= self.view.len() { 0 } else { i + 1 };
+            self.todo_list_state.select(Some(next_index));
+        };
+    }
+
+    pub fn navigate_up(&mut self) {
+        if let Some(i) = self.todo_list_state.selected() {
+            let next_index = if i == 0 {
+                self.view.len().saturating_sub(1)
+            } else {
+                i - 1
+            };
+            self.todo_list_state.select(Some(next_index));
+        };
+    }
+
+    /// Moves `count` rows down, stopping at the last one. Moving a single
+    /// row wraps around like [`navigate_down`](App::navigate_down).
+    pub fn navigate_down_by(&mut self, count: usize) {
+        match (count, self.todo_list_state.selected()) {
+            (1, _) => self.navigate_down(),
+            (_, Some(i)) => self.navigate_to_row(i.saturating_add(count)),
+            (_, None) => {}
+        }
+    }
+
+    /// Moves `count` rows up, stopping at the first one. Moving a single row
+    /// wraps around like [`navigate_up`](App::navigate_up).
+    pub fn navigate_up_by(&mut self, count: usize) {
+        match (count, self.todo_list_state.selected()) {
+            (1, _) => self.navigate_up(),
+            (_, Some(i)) => self.navigate_to_row(i.saturating_sub(count)),
+            (_, None) => {}
+        }
+    }
+
+    /// Selects `row`, or the last row if there are fewer.
+    pub fn navigate_to_row(&mut self, row: usize) {
+        if !self.view.is_empty() {
+            self.todo_list_state
+                .select(Some(std::cmp::min(row, self.view.len() - 1)));
+        }
+    }
+
+    pub fn navigate_top(&mut self) {
+        if !self.view.is_empty() {
+            self.todo_list_state.select(Some(0));
+        }
+    }
+
+    pub fn navigate_bottom(&mut self) {
+        self.select_last_task();
+    }
+
+    /// Selects `row` for a click on it and focuses the list. Returns true
+    /// if the row was clicked twice in quick succession.
+    pub fn click_row(&mut self, row: usize) -> bool {
+        self.navigate_to_row(row);
+        self.focus = Focus::List;
+        let now = Instant::now();
+        let double = self.last_click.is_some_and(|(last_row, at)| {
+            last_row == row && now.duration_since(at) < DOUBLE_CLICK_TIME
+        });
+        // a third click starts over
+        self.last_click = if double { None } else { Some((row, now)) };
+        double
+    }
+
+    /// Moves the selection by a row for the scroll wheel, without wrapping
+    /// around.
+    pub fn scroll_list(&mut self, down: bool) {
+        match self.todo_list_state.selected() {
+            Some(row) if down => self.navigate_to_row(row + 1),
+            Some(row) => self.navigate_to_row(row.saturating_sub(1)),
+            None => self.navigate_top(),
+        }
+    }
+
+    /// Starts selecting rows from the selected one.
+    pub fn enter_visual_line(&mut self) {
+        if let Some(row) = self.todo_list_state.selected() {
+            self.selection_anchor = Some(row);
+            self.mode = AppMode::VisualLine;
+        }
+    }
+
+    /// Ends the visual line selection, if there is one.
+    pub fn exit_visual_line(&mut self) {
+        self.selection_anchor = None;
+        if self.mode == AppMode::VisualLine {
+            self.mode = AppMode::Visual;
+        }
+    }
+
+    pub fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            Focus::List => Focus::Calendar,
+            Focus::Calendar => Focus::List,
+        };
+    }
+
+    pub fn focus_list(&mut self) {
+        self.focus = Focus::List;
+    }
+
+    pub fn calendar_move_days(&mut self, days: i64) {
+        if let Some(date) = self
+            .calendar_date
+            .checked_add(time::Duration::days(days))
+        {
+            self.calendar_date = date;
+        }
+    }
+
+    pub fn calendar_move_months(&mut self, months: i32) {
+        self.calendar_date = due::add_months(self.calendar_date, months);
+    }
+
+    /// Puts the calendar cursor on `date` and focuses the calendar.
+    pub fn focus_calendar_date(&mut self, date: Date) {
+        self.calendar_date = date;
+        self.focus = Focus::Calendar;
+    }
+
+    pub fn calendar_today(&mut self) {
+        self.calendar_date = due::today();
+    }
+
+    /// Filters the list to tasks due on the calendar cursor's day, or clears
+    /// the filter if it already shows that day.
+    pub fn filter_by_calendar_date(&mut self) {
+        let date = self.calendar_date;
+        if self.view.get_filter().get_due() == Some(date) {
+            self.clear_date_filter();
+            return;
+        }
+
+        self.filter_by_due(date);
+        self.focus = Focus::List;
+    }
+
+    pub fn filter_by_due(&mut self, date: Date) {
+        self.view.get_filter_mut().set_due(Some(date));
+        self.view.refresh(self.todo.active_list());
+        self.todo_list_state
+            .select(if self.view.is_empty() { None } else { Some(0) });
+        self.action_display
+            .set(&format!("Showing tasks due {}", due::format_date(date)));
+    }
+
+    /// Shows only tasks carrying all of `tags`.
+    pub fn filter_by_tags(&mut self, tags: Vec<String>) {
+        let filter = self.view.get_filter_mut();
+        filter.clear_tags();
+        for tag in &tags {
+            filter.toggle_tag(tag);
+        }
+        self.view.refresh(self.todo.active_list());
+        self.todo_list_state
+            .select(if self.view.is_empty() { None } else { Some(0) });
+        self.set_tag_filter_action();
+    }
+
+    pub fn clear_filter(&mut self) {
+        let selected = self.selected_path();
+        let filter = self.view.get_filter_mut();
+        filter.set_due(None);
+        filter.clear_tags();
+        self.view.refresh(self.todo.active_list());
+        match selected {
+            Some(path) => self.select_task(&path),
+            None => self.navigate_top(),
+        }
+        self.action_display.set("Showing all tasks");
+    }
+
+    pub fn clear_date_filter(&mut self) {
+        let selected = self.selected_path();
+        self.view.get_filter_mut().set_due(None);
+        self.view.refresh(self.todo.active_list());
+        match selected {
+            Some(path) => self.select_task(&path),
+            None => self.navigate_top(),
+        }
+        self.action_display.set("Showing all tasks");
+    }
+
+    pub fn enter_tag_picker(&mut self) {
+        if self.get_tag_counts().is_empty() {
+            self.action_display.set("No tags in this list");
+            return;
+        }
+        self.tag_picker_state.select(Some(0));
+        self.mode = AppMode::TagPicker;
+    }
+
+    pub fn exit_tag_picker(&mut self) {
+        self.mode = AppMode::Visual;
+    }
+
+    pub fn tag_picker_down(&mut self) {
+        let len = self.get_tag_counts().len();
+        if let Some(i) = self.tag_picker_state.selected() {
+            self.tag_picker_state
+                .select(Some(if i + 1 >= len { 0 } else { i + 1 }));
+        }
+    }
+
+    pub fn tag_picker_up(&mut self) {
+        let len = self.get_tag_counts().len();
+        if let Some(i) = self.tag_picker_state.selected() {
+            self.tag_picker_state
+                .select(Some(if i == 0 { len.saturating_sub(1) } else { i - 1 }));
+        }
+    }
+
+    /// Lists the backups of the todo file to pick one to restore, for
+    /// `:recover`.
+    pub fn enter_backup_picker(&mut self) -> Result<(), String> {
+        let backups = todo_serializer::backups(Path::new(&self.file_path));
+        if backups.is_empty() {
+            return Err(format!("No backups of {}", self.file_path));
+        }
+        self.backups = backups
+            .into_iter()
+            .map(|backup| {
+                let (todo, load_error) = load_todo(&backup.path.to_string_lossy());
+                let count = todo.get_lists().iter().map(|list| list.all_tasks().len()).sum();
+                (backup, load_error.is_none().then_some(count))
+            })
+            .collect();
+        self.backup_picker_state.select(Some(0));
+        self.mode = AppMode::Recover;
+        Ok(())
+    }
+
+    pub fn exit_backup_picker(&mut self) {
+        self.mode = AppMode::Visual;
+    }
+
+    pub fn backup_picker_down(&mut self) {
+        let len = self.backups.len();
+        if let Some(i) = self.backup_picker_state.selected() {
+            self.backup_picker_state
+                .select(Some(if i + 1 >= len { 0 } else { i + 1 }));
+        }
+    }
+
+    pub fn backup_picker_up(&mut self) {
+        let len = self.backups.len();
+        if let Some(i) = self.backup_picker_state.selected() {
+            self.backup_picker_state
+                .select(Some(if i == 0 { len.saturating_sub(1) } else { i - 1 }));
+        }
+    }
+
+    /// Replaces the todo file with the backup under the picker cursor and
+    /// opens it. This can't be undone, but the replaced file is kept.
+    pub fn restore_picked_backup(&mut self) {
+        let Some((backup, count)) = self
+            .backup_picker_state
+            .selected()
+            .and_then(|i| self.backups.get(i))
+            .cloned()
+        else {
+            return;
+        };
+        if count.is_none() {
+            self.action_display.set("That backup can't be read either");
+            return;
+        }
+        if let Err(e) = self.can_write() {
+            self.action_display.set(&e);
+            return;
+        }
+
+        self.mode = AppMode::Visual;
+        if let Err(e) = todo_serializer::restore(Path::new(&self.file_path), &backup) {
+            self.action_display
+                .set(&format!("Error restoring backup: {}", e));
+            return;
+        }
+        let (todo, load_error) = load_todo(&self.file_path);
+        self.todo = todo;
+        self.load_error = load_error;
+        self.mark_synced();
+        self.history = History::new();
+        self.view.refresh(self.todo.active_list());
+        self.todo_list_state.select(None);
+        self.navigate_top();
+        self.action_display.set(&format!(
+            "Restored backup from {}",
+            backup.taken.format(BACKUP_TIME_FORMAT).unwrap_or_default()
+        ));
+    }
+
+    /// Adds the tag under the picker cursor to the filter, or removes it.
+    pub fn toggle_picked_tag(&mut self) {
+        let tags = self.get_tag_counts();
+        let Some((tag, _)) = self
+            .tag_picker_state
+            .selected()
+            .and_then(|i| tags.get(i))
+        else {
+            return;
+        };
+
+        self.view.get_filter_mut().toggle_tag(tag);
+        self.view.refresh(self.todo.active_list());
+        self.todo_list_state
+            .select(if self.view.is_empty() { None } else { Some(0) });
+        self.set_tag_filter_action();
+    }
+
+    pub fn clear_tag_filter(&mut self) {
+        let selected = self.selected_path();
+        self.view.get_filter_mut().clear_tags();
+        self.view.refresh(self.todo.active_list());
+        match selected {
+            Some(path) => self.select_task(&path),
+            None => self.navigate_top(),
+        }
+        self.action_display.set("Showing all tags");
+    }
+
+    /// Adds `tags` to the selected task, or to every task in the visual
+    /// line selection.
+    pub fn tag_task(&mut self, tags: &[String]) -> Result<(), String> {
+        let selected = self.selected_path().ok_or("No task selected")?;
+        let ids = self.take_target_ids(1);
+
+        self.record_history();
+        for id in &ids {
+            let path = self.todo.active_list().find(id).expect("selected task exists");
+            let task = self.todo.active_list().get_task(&path);
+            let mut title = task.title().trim_end().to_string();
+            for tag in tags {
+                if !task.has_tag(tag) {
+                    title.push(' ');
+                    title.push_str(tag);
+                }
+            }
+            let list = self.todo.active_list_mut();
+            list.edit_task(&path, title.trim_start().to_string());
+            list.update_tags(&path);
+        }
+        self.select_task(&selected);
+        self.action_display.set(&match ids.len() {
+            1 => format!("Tagged {}", tags.join(" ")),
+            n => format!("Tagged {} tasks {}", n, tags.join(" ")),
+        });
+        Ok(())
+    }
+
+    pub fn enter_command_mode(&mut self) {
+        self.command_line.clear();
+        self.mode = AppMode::Command;
+    }
+
+    pub fn push_to_command(&mut self, c: char) {
+        self.command_line.push(c);
+    }
+
+    /// Deletes the last character, or leaves command mode if there is
+    /// none.
+    pub fn pop_from_command(&mut self) {
+        if !self.command_line.pop() {
+            self.cancel_command();
+        }
+    }
+
+    pub fn cancel_command(&mut self) {
+        self.command_line.clear();
+        self.selection_anchor = None;
+        self.mode = AppMode::Visual;
+    }
+
+    pub fn complete_command(&mut self, forward: bool) {
+        let mut command_line = std::mem::take(&mut self.command_line);
+        command_line.complete(forward, |line| command::completions(self, line));
+        self.command_line = command_line;
+    }
+
+    pub fn command_history_previous(&mut self) {
+        self.command_line.history_previous();
+    }
+
+    pub fn command_history_next(&mut self) {
+        self.command_line.history_next();
+    }
+
+    /// Runs the typed command through the command registry.
+    pub fn submit_command(&mut self) {
+        let line = self.command_line.submit();
+        self.mode = AppMode::Visual;
+        if let Err(e) = command::execute(self, &line) {
+            self.action_display.set(&e);
+        }
+        // commands that don't act on a visual line selection still end it
+        self.selection_anchor = None;
+    }
+
+    /// Saves now and says so, for `:w`.
+    pub fn write(&mut self) -> Result<(), String> {
+        if self.load_error.is_some() {
+            return Err("Not saved: todo file failed to load".to_string());
+        }
+        self.can_write()?;
+        // writes over changes made elsewhere, as :w means to
+        self.todo
+            .save()
+            .map_err(|e| format!("Error saving todo: {}", e))?;
+        self.mark_synced();
+        self.action_display.set(&format!("Wrote {}", self.file_path));
+        Ok(())
+    }
+
+    /// Switches to the todo saved at `path`. The current one is saved
+    /// first, unless `force` drops its unsaved changes.
+    pub fn open_file(&mut self, path: &str, force: bool) -> Result<(), String> {
+        if !force {
+            self.save_before_leaving()?;
+        }
+
+        let (todo, load_error) = load_todo(path);
+        self.todo = todo;
+        self.file_path = path.to_string();
+        self.history = History::new();
+        self.view = TodoView::new();
+        self.view.refresh(self.todo.active_list());
+        self.todo_list_state.select(None);
+        self.navigate_top();
+        match &load_error {
+            Some(e) => self.action_display.set(&format!("Error loading todo: {}", e)),
+            None => self.action_display.set(&format!("Opened {}", path)),
+        }
+        self.load_error = load_error;
+        self.mark_synced();
+        self.read_only = None;
+        self.lock_file(false);
+        Ok(())
+    }
+
+    /// Reads the todo file again, for `:e` without a file. Changes not
+    /// saved yet are only dropped with `force`.
+    pub fn reload(&mut self, force: bool) -> Result<(), String> {
+        if !force && self.has_unsaved_changes() {
+            return Err("No write since last change (:e! to drop it)".to_string());
+        }
+        let (todo, load_error) = load_todo(&self.file_path);
+        if let Some(e) = load_error {
+            return Err(format!("Error loading todo: {}", e));
+        }
+        self.todo = todo;
+        self.load_error = None;
+        self.history = History::new();
+        self.refresh_view();
+        self.mark_synced();
+        self.action_display.set(&format!("Reloaded {}", self.file_path));
+        Ok(())
+    }
+
+    /// Whether there are changes that saving would keep. A todo that
+    /// failed to load is never saved, so it has none.
+    fn has_unsaved_changes(&self) -> bool {
+        self.load_error.is_none() && self.disk_snapshot.as_ref() != Some(&self.todo.snapshot())
+    }
+
+    /// Saves the todo before another file replaces it, as [`App::save`]
+    /// would, or says why it can't be.
+    fn save_before_leaving(&mut self) -> Result<(), String> {
+        if self.file_changed || matches!(self.confirm, Some(Confirm::MergeFile(..))) {
+            return Err(format!(
+                "{} changed outside todo-term, merge it first",
+                self.file_path
+            ));
+        }
+        if !self.has_unsaved_changes() {
+            return Ok(());
+        }
+        self.can_write()
+            .map_err(|e| format!("{} (:e! to drop changes)", e))?;
+        if !self.check_file() {
+            return Err(format!(
+                "Not saved: {} changed outside todo-term",
+                self.file_path
+            ));
+        }
+        self.todo
+            .save()
+            .map_err(|e| format!("Error saving todo: {}", e))?;
+        self.mark_synced();
+        Ok(())
+    }
+
+    /// Writes every list to `path`, as JSON or as a Markdown checklist
+    /// depending on the extension.
+    pub fn export(&mut self, path: &str) -> Result<(), String> {
+        todo_serializer::export(&self.todo, path)
+            .map_err(|e| format!("Error exporting todo: {}", e))?;
+        self.action_display.set(&format!("Exported to {}", path));
+        Ok(())
+    }
+
+    pub fn toggle_help(&mut self) {
+        self.show_help = !self.show_help;
+    }
+
+    pub fn is_help_visible(&self) -> bool {
+        self.show_help
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn is_todo_empty(&self) -> bool {
+        self.todo.active_list().is_empty()
+    }
+
+    pub fn get_todo_list_state(&mut self) -> &mut ListState {
+        &mut self.todo_list_state
+    }
+
+    pub fn is_sorted_by_priority(&self) -> bool {
+        self.todo.active_list().is_sorted_by_priority()
+    }
+
+    pub fn get_list_names(&self) -> Vec<&str> {
+        self.todo
+            .get_lists()
+            .iter()
+            .map(|list| list.get_name())
+            .collect()
+    }
+
+    pub fn get_active_list_index(&self) -> usize {
+        self.todo.active_list_index()
+    }
+
+    pub fn get_active_list_name(&self) -> &str {
+        self.todo.active_list().get_name()
+    }
+
+    pub fn get_view(&self) -> &TodoView {
+        &self.view
+    }
+
+    pub fn get_task(&self, path: &TaskPath) -> &Task {
+        self.todo.active_list().get_task(path)
+    }
+
+    pub fn get_selected_task(&self) -> Option<&Task> {
+        self.selected_path().map(|path| self.get_task(&path))
+    }
+
+    pub fn all_tasks(&self) -> Vec<&Task> {
+        self.todo.active_list().all_tasks()
+    }
+
+    /// The tags of the open list with their task counts, for the tag
+    /// picker. Filtered tags the list does not use are included with a
+    /// count of zero so they can still be removed.
+    pub fn get_tag_counts(&self) -> Vec<(String, usize)> {
+        let mut counts = self.todo.active_list().tag_counts();
+        for tag in self.view.get_filter().get_tags() {
+            if !counts.iter().any(|(t, _)| t == tag) {
+                counts.push((tag.clone(), 0));
+            }
+        }
+        counts.sort();
+        counts
+    }
+
+    pub fn get_keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    pub fn get_pending_keys(&self) -> &str {
+        &self.pending_keys
+    }
+
+    pub fn set_pending_keys(&mut self, pending_keys: String) {
+        self.pending_keys = pending_keys;
+    }
+
+    pub fn get_command_line(&self) -> &str {
+        self.command_line.get_input()
+    }
+
+    pub fn get_file_path(&self) -> &str {
+        &self.file_path
+    }
+
+    pub fn get_search(&self) -> &Search {
+        &self.search
+    }
+
+    /// What the footer prompt is asking for.
+    pub fn get_prompt_label(&self) -> String {
+        match self.mode {
+            AppMode::Prompt(PromptKind::Search) if self.search.is_regex() => {
+                "Search (regex)".to_string()
+            }
+            AppMode::Prompt(kind) => kind.to_string(),
+            mode => mode.to_string(),
+        }
+    }
+
+    pub fn get_tag_picker_state(&mut self) -> &mut ListState {
+        &mut self.tag_picker_state
+    }
+
+    pub fn get_backups(&self) -> &[(Backup, Option<usize>)] {
+        &self.backups
+    }
+
+    pub fn get_backup_picker_state(&mut self) -> &mut ListState {
+        &mut self.backup_picker_state
+    }
+
+    /// The rows of the visual line selection.
+    pub fn get_selection(&self) -> Option<RangeInclusive<usize>> {
+        let anchor = self.selection_anchor?;
+        let row = self.todo_list_state.selected()?;
+        Some(std::cmp::min(anchor, row)..=std::cmp::max(anchor, row))
+    }
+
+    pub fn get_focus(&self) -> Focus {
+        self.focus
+    }
+
+    pub fn get_calendar_date(&self) -> Date {
+        self.calendar_date
+    }
+
+    pub fn get_complete_tasks(&self) -> &Vec<Task> {
+        self.todo.active_list().get_complete_tasks()
+    }
+
+    pub fn get_incomplete_tasks(&self) -> &Vec<Task> {
+        self.todo.active_list().get_incomplete_tasks()
+    }
+
+    pub fn get_mode(&self) -> AppMode {
+        self.mode
+    }
+
+    pub fn get_hit_map(&self) -> &HitMap {
+        &self.hit_map
+    }
+
+    pub fn get_hit_map_mut(&mut self) -> &mut HitMap {
+        &mut self.hit_map
+    }
+
+    pub fn get_line_editor(&self) -> &LineEditor {
+        &self.line_editor
+    }
+
+    pub fn get_prompt(&self) -> &str {
+        &self.prompt
+    }
+
+    pub fn get_confirm_question(&self) -> Option<String> {
+        match self.confirm.as_ref()? {
+            Confirm::CompleteSubtasks(ids) => {
+                let list = self.todo.active_list();
+                let open = ids
+                    .iter()
+                    .filter_map(|id| list.find(id))
+                    .map(|path| list.get_task(&path))
+                    .filter(|task| !task.is_complete())
+                    .map(|task| {
+                        let (done, total) = task.progress();
+                        total - done
+                    })
+                    .sum::<usize>();
+                Some(format!("Complete {} subtasks too? (y/n)", open))
+            }
+            Confirm::MergeFile(..) => Some(format!(
+                "{} changed elsewhere. Merge, or keep yours? (y/n)",
+                self.file_path
+            )),
+            Confirm::TakeOverFile(pid) => Some(format!(
+                "{} is open in todo-term pid {}. Take it over? (y/n)",
+                self.file_path, pid
+            )),
+            Confirm::DeleteList => Some(format!(
+                "Delete list {} and its {} tasks? (y/n)",
+                self.todo.active_list().get_name(),
+                self.todo.active_list().all_tasks().len()
+            )),
+        }
+    }
+
+    pub fn get_action(&self) -> &str {
+        self.action_display.get()
+    }
+
+    pub fn get_load_error(&self) -> Option<&str> {
+        self.load_error.as_deref()
+    }
+
+    pub fn save(&mut self) {
+        if self.load_error.is_some() {
+            self.action_display
+                .set("Not saved: todo file failed to load");
+            return;
+        }
+        if let Err(e) = self.can_write() {
+            // nothing to say while there is nothing to save
+            if self.disk_snapshot.as_ref() != Some(&self.todo.snapshot()) {
+                self.action_display.set(&e);
+            }
+            return;
+        }
+
+        if !self.check_file() {
+            return;
+        }
+        match self.todo.save() {
+            Ok(()) => self.mark_synced(),
+            Err(e) => self
+                .action_display
+                .set(&format!("Error saving todo: {}", e)),
+        }
+    }
+
+    fn select_last_task(&mut self) {
+        let selected = match self.view.len() {
+            0 => None,
+            n => Some(n - 1),
+        };
+        self.todo_list_state.select(selected);
+    }
+
+    /// The task path of the selected row.
+    fn selected_path(&self) -> Option<TaskPath> {
+        self.todo_list_state
+            .selected()
+            .and_then(|row| self.view.task_path(row))
+            .cloned()
+    }
+
+    /// Starts editing the title of the selected task with the cursor at its
+    /// end.
+    fn load_line_editor(&mut self) {
+        let title = match self.selected_path() {
+            Some(path) => self.todo.active_list().get_task(&path).title().to_string(),
+            None => String::new(),
+        };
+        self.line_editor.set_text(&title);
+    }
+
+    /// The ids of the rows an action applies to: the visual line
+    /// selection, which is ended, or `count` rows from the selected one
+    /// down.
+    fn take_target_ids(&mut self, count: usize) -> Vec<String> {
+        let rows = match self.get_selection() {
+            Some(rows) => rows,
+            None => match self.todo_list_state.selected() {
+                Some(row) => row..=row.saturating_add(count - 1),
+                None => return Vec::new(),
+            },
+        };
+        self.exit_visual_line();
+
+        let list = self.todo.active_list();
+        self.view
+            .rows()
+            .iter()
+            .skip(*rows.start())
+            .take(rows.end() - rows.start() + 1)
+            .map(|path| list.get_task(path).id().to_string())
+            .collect()
+    }
+
+    /// Toggles the tasks with `ids`. A task that already changed, because
+    /// it was completed along with a parent toggled before it, is left as
+    /// it is.
+    fn toggle_task_ids(&mut self, ids: &[String], with_subtasks: bool) {
+        let list = self.todo.active_list();
+        let targets = ids
+            .iter()
+            .filter_map(|id| list.find(id).map(|path| (id, !list.get_task(&path).is_complete())))
+            .collect::<Vec<_>>();
+        for (id, complete) in targets {
+            if let Some(path) = self.todo.active_list().find(id) {
+                if self.todo.active_list().get_task(&path).is_complete() != complete {
+                    self.todo.active_list_mut().toggle_task(&path, with_subtasks);
+                }
+            }
+        }
+        self.refresh_view();
+        self.action_display.set(&match ids.len() {
+            1 => "Toggled task".to_string(),
+            n => format!("Toggled {} tasks", n),
+        });
+    }
+
+    /// Inserts `task` as a sibling `offset` places after the selected task
+    /// and selects it wherever it ends up. Top-level tasks are kept within
+    /// the incomplete tasks.
+    fn insert_task(&mut self, offset: usize, task: Task) {
+        let incomplete_len = self.todo.active_list().get_incomplete_tasks().len();
+        let path = match self.selected_path() {
+            Some(path) if path.depth() > 0 => path.sibling(path.index() + offset),
+            Some(path) => TaskPath::root(std::cmp::min(path.index() + offset, incomplete_len)),
+            None => TaskPath::root(incomplete_len),
+        };
+        let id = task.id().to_string();
+        self.todo.active_list_mut().add_task(&path, task);
+        self.select_task_id(&id);
+    }
+
+    /// A task with `title` that is visible under the current filter.
+    /// Filtered tags it lacks are written in front of the title so they
+    /// survive the edit.
+    fn new_task(&self, title: &str) -> Task {
+        let filter = self.view.get_filter();
+        let has = tags::parse_tags(title);
+        let prefix = filter
+            .get_tags()
+            .iter()
+            .filter(|tag| !has.contains(tag))
+            .map(|tag| format!("{} ", tag))
+            .collect::<String>();
+        let mut task = Task::new(format!("{}{}", prefix, title));
+        task.set_due(filter.get_due());
+        task
+    }
+
+    /// Refreshes the view and selects the task at `path`, or the nearest
+    /// row if the filter hides it.
+    fn select_task(&mut self, path: &TaskPath) {
+        self.view.refresh(self.todo.active_list());
+        match self.view.row_of(path) {
+            Some(row) => self.todo_list_state.select(Some(row)),
+            None => self.clamp_selection(),
+        }
+    }
+
+    /// Like [`select_task`](App::select_task), for a task that may have
+    /// moved.
+    fn select_task_id(&mut self, id: &str) {
+        match self.todo.active_list().find(id) {
+            Some(path) => self.select_task(&path),
+            None => self.refresh_view(),
+        }
+    }
+
+    /// Refreshes the view after the todo changed, keeping the selected row
+    /// in range.
+    fn refresh_view(&mut self) {
+        self.view.refresh(self.todo.active_list());
+        self.clamp_selection();
+    }
+
+    fn set_tag_filter_action(&mut self) {
+        let tags = self.view.get_filter().get_tags();
+        if tags.is_empty() {
+            self.action_display.set("Showing all tags");
+        } else {
+            self.action_display
+                .set(&format!("Showing {}", tags.join(" ")));
+        }
+    }
+
+    fn set_priority_action(&mut self, id: &str) {
+        let priority = self
+            .todo
+            .active_list()
+            .find(id)
+            .and_then(|path| self.todo.active_list().get_task(&path).priority());
+        let action = match priority {
+            Some(Priority::High) => "Priority high",
+            Some(Priority::Medium) => "Priority medium",
+            Some(Priority::Low) => "Priority low",
+            None => "Priority cleared",
+        };
+        self.action_display.set(action);
+    }
+
+    fn clamp_selection(&mut self) {
+        match self.todo_list_state.selected() {
+            Some(row) if row < self.view.len() => {}
+            Some(_) => self.select_last_task(),
+            None => self.navigate_top(),
+        }
+    }
+
+    /// Records the current state as an undo step. Changes made during an
+    /// insert session belong to the step recorded when it started.
+    fn record_history(&mut self) {
+        if self.mode == AppMode::Insert {
+            return;
+        }
+        let snapshot = self.snapshot();
+        self.history.record(snapshot);
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            todo: self.todo.snapshot(),
+            selected: self.todo_list_state.selected(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.todo.restore(snapshot.todo);
+        self.todo_list_state.select(snapshot.selected);
+        self.refresh_view();
     }
 }
@@ -0,0 +1,449 @@
+use crate::{app::App, due, tags};
+
+/// The text typed after `:`, with its history and tab completion.
+#[derive(Debug, Default)]
+pub struct CommandLine {
+    input: String,
+    history: Vec<String>,
+    /// The history entry shown, while browsing it.
+    history_index: Option<usize>,
+    /// What was typed before browsing the history.
+    draft: String,
+    completion: Option<Completion>,
+}
+
+/// Tab completion in progress: the candidates for the word starting at
+/// `start`, and which one is shown.
+#[derive(Debug)]
+struct Completion {
+    start: usize,
+    candidates: Vec<String>,
+    index: usize,
+}
+
+impl CommandLine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_input(&self) -> &str {
+        &self.input
+    }
+
+    pub fn clear(&mut self) {
+        self.input.clear();
+        self.history_index = None;
+        self.completion = None;
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.input.push(c);
+        self.completion = None;
+    }
+
+    /// Removes the last character. Returns false if there was none.
+    pub fn pop(&mut self) -> bool {
+        self.completion = None;
+        self.input.pop().is_some()
+    }
+
+    /// Takes the input and remembers it in the history.
+    pub fn submit(&mut self) -> String {
+        let line = std::mem::take(&mut self.input);
+        if !line.trim().is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        self.clear();
+        line
+    }
+
+    pub fn history_previous(&mut self) {
+        let index = match self.history_index {
+            Some(0) => return,
+            Some(index) => index - 1,
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = self.input.clone();
+                self.history.len() - 1
+            }
+        };
+        self.history_index = Some(index);
+        self.input = self.history[index].clone();
+        self.completion = None;
+    }
+
+    pub fn history_next(&mut self) {
+        let Some(index) = self.history_index else {
+            return;
+        };
+        if index + 1 < self.history.len() {
+            self.history_index = Some(index + 1);
+            self.input = self.history[index + 1].clone();
+        } else {
+            self.history_index = None;
+            self.input = std::mem::take(&mut self.draft);
+        }
+        self.completion = None;
+    }
+
+    /// Replaces the last word with the next or previous candidate. The
+    /// candidates are looked up on the first press and cycled after that.
+    pub fn complete(&mut self, forward: bool, candidates: impl FnOnce(&str) -> Vec<String>) {
+        let completion = match self.completion.take() {
+            Some(mut completion) => {
+                let len = completion.candidates.len();
+                completion.index = if forward {
+                    (completion.index + 1) % len
+                } else {
+                    (completion.index + len - 1) % len
+                };
+                completion
+            }
+            None => {
+                let candidates = candidates(&self.input);
+                if candidates.is_empty() {
+                    return;
+                }
+                Completion {
+                    start: self.input.rfind(' ').map_or(0, |i| i + 1),
+                    index: if forward { 0 } else { candidates.len() - 1 },
+                    candidates,
+                }
+            }
+        };
+        self.input.truncate(completion.start);
+        self.input.push_str(&completion.candidates[completion.index]);
+        self.completion = Some(completion);
+    }
+}
+
+/// What the arguments of a command complete to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArgKind {
+    None,
+    File,
+    Tag,
+    Filter,
+    Sort,
+}
+
+/// What a command does with the words typed after it.
+pub type Run = fn(&mut App, &[&str]) -> Result<(), String>;
+
+/// An entry of the command registry.
+pub struct Command {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub usage: &'static str,
+    pub args: ArgKind,
+    pub run: Run,
+    /// What the command does when its name ends in `!`, if it has that form.
+    pub force: Option<Run>,
+}
+
+/// Every command that can be typed after `:`.
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "write",
+        aliases: &["w"],
+        usage: ":w",
+        args: ArgKind::None,
+        run: write,
+        force: None,
+    },
+    Command {
+        name: "quit",
+        aliases: &["q"],
+        usage: ":q",
+        args: ArgKind::None,
+        run: quit,
+        force: None,
+    },
+    Command {
+        name: "wq",
+        aliases: &["x"],
+        usage: ":wq",
+        args: ArgKind::None,
+        run: write_quit,
+        force: None,
+    },
+    Command {
+        name: "edit",
+        aliases: &["e"],
+        usage: ":e[!] [file]",
+        args: ArgKind::File,
+        run: edit,
+        force: Some(edit_force),
+    },
+    Command {
+        name: "sort",
+        aliases: &[],
+        usage: ":sort [priority|manual]",
+        args: ArgKind::Sort,
+        run: sort,
+        force: None,
+    },
+    Command {
+        name: "filter",
+        aliases: &[],
+        usage: ":filter due <date> | tag <tags> | clear",
+        args: ArgKind::Filter,
+        run: filter,
+        force: None,
+    },
+    Command {
+        name: "export",
+        aliases: &[],
+        usage: ":export <file>",
+        args: ArgKind::File,
+        run: export,
+        force: None,
+    },
+    Command {
+        name: "recover",
+        aliases: &[],
+        usage: ":recover",
+        args: ArgKind::None,
+        run: recover,
+        force: None,
+    },
+    Command {
+        name: "takeover",
+        aliases: &[],
+        usage: ":takeover",
+        args: ArgKind::None,
+        run: take_over,
+        force: None,
+    },
+    Command {
+        name: "tag",
+        aliases: &[],
+        usage: ":tag <tags>",
+        args: ArgKind::Tag,
+        run: tag,
+        force: None,
+    },
+];
+
+/// Finds a command by name, alias, or a prefix of exactly one name.
+pub fn find(name: &str) -> Result<&'static Command, String> {
+    if let Some(command) = COMMANDS
+        .iter()
+        .find(|c| c.name == name || c.aliases.contains(&name))
+    {
+        return Ok(command);
+    }
+
+    let matches = COMMANDS
+        .iter()
+        .filter(|c| c.name.starts_with(name))
+        .collect::<Vec<_>>();
+    match matches.as_slice() {
+        [command] => Ok(command),
+        [] => Err(format!("Not a command: {}", name)),
+        _ => Err(format!("Ambiguous command: {}", name)),
+    }
+}
+
+/// Parses and runs a command line.
+pub fn execute(app: &mut App, line: &str) -> Result<(), String> {
+    let mut words = line.split_whitespace();
+    let Some(name) = words.next() else {
+        return Ok(());
+    };
+    let args = words.collect::<Vec<_>>();
+    let run = match name.strip_suffix('!') {
+        Some(name) => find(name)?
+            .force
+            .ok_or_else(|| format!("Not a command: {}!", name))?,
+        None => find(name)?.run,
+    };
+    run(app, &args)
+}
+
+/// Candidates for the last word of `line`.
+pub fn completions(app: &App, line: &str) -> Vec<String> {
+    let words = line.split(' ').collect::<Vec<_>>();
+    let (last, previous) = words.split_last().expect("split yields a word");
+
+    let candidates = match previous {
+        [] => COMMANDS.iter().map(|c| c.name.to_string()).collect(),
+        [name, args @ ..] => match find(name.trim_end_matches('!')).map(|c| c.args) {
+            Ok(ArgKind::File) if args.is_empty() => return file_completions(last),
+            Ok(ArgKind::Tag) => tag_names(app),
+            Ok(ArgKind::Sort) if args.is_empty() => words_of(&["priority", "manual"]),
+            Ok(ArgKind::Filter) => match args {
+                [] => words_of(&["due", "tag", "clear"]),
+                ["tag", ..] => tag_names(app),
+                ["due"] => words_of(&["today", "tomorrow"]),
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        },
+    };
+
+    let last = last.to_lowercase();
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.to_lowercase().starts_with(&last))
+        .collect()
+}
+
+fn words_of(words: &[&str]) -> Vec<String> {
+    words.iter().map(|word| word.to_string()).collect()
+}
+
+fn tag_names(app: &App) -> Vec<String> {
+    app.get_tag_counts().into_iter().map(|(tag, _)| tag).collect()
+}
+
+/// Files and directories next to the typed path that start with its last
+/// component. Directories end in `/`.
+fn file_completions(typed: &str) -> Vec<String> {
+    let (dir, prefix) = match typed.rfind('/') {
+        Some(i) => (&typed[..=i], &typed[i + 1..]),
+        None => ("", typed),
+    };
+    let read_dir = if dir.is_empty() { "." } else { dir };
+    let Ok(entries) = std::fs::read_dir(read_dir) else {
+        return Vec::new();
+    };
+
+    let mut candidates = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", dir, name, slash))
+        })
+        .collect::<Vec<_>>();
+    candidates.sort();
+    candidates
+}
+
+/// A tag as typed after `:tag` or `:filter tag`, with `#` added when it
+/// has no sigil.
+fn parse_tag(word: &str) -> Result<String, String> {
+    let tag = if word.starts_with(tags::TAG_SIGILS) {
+        word.to_lowercase()
+    } else {
+        format!("#{}", word.to_lowercase())
+    };
+    match tags::parse_tags(&tag).as_slice() {
+        [parsed] if *parsed == tag => Ok(tag),
+        _ => Err(format!("Not a tag: {}", word)),
+    }
+}
+
+fn usage(name: &str) -> String {
+    let command = COMMANDS
+        .iter()
+        .find(|c| c.name == name)
+        .expect("usage of a registered command");
+    format!("Usage: {}", command.usage)
+}
+
+fn no_args(args: &[&str]) -> Result<(), String> {
+    match args {
+        [] => Ok(()),
+        _ => Err("Too many arguments".to_string()),
+    }
+}
+
+fn write(app: &mut App, args: &[&str]) -> Result<(), String> {
+    no_args(args)?;
+    app.write()
+}
+
+fn quit(app: &mut App, args: &[&str]) -> Result<(), String> {
+    no_args(args)?;
+    app.quit();
+    Ok(())
+}
+
+fn write_quit(app: &mut App, args: &[&str]) -> Result<(), String> {
+    no_args(args)?;
+    app.write()?;
+    app.quit();
+    Ok(())
+}
+
+fn edit(app: &mut App, args: &[&str]) -> Result<(), String> {
+    match args {
+        [] => app.reload(false),
+        [path] => app.open_file(path, false),
+        _ => Err("Too many arguments".to_string()),
+    }
+}
+
+fn edit_force(app: &mut App, args: &[&str]) -> Result<(), String> {
+    match args {
+        [] => app.reload(true),
+        [path] => app.open_file(path, true),
+        _ => Err("Too many arguments".to_string()),
+    }
+}
+
+fn recover(app: &mut App, args: &[&str]) -> Result<(), String> {
+    no_args(args)?;
+    app.enter_backup_picker()
+}
+
+fn take_over(app: &mut App, args: &[&str]) -> Result<(), String> {
+    no_args(args)?;
+    app.take_over_file()
+}
+
+fn sort(app: &mut App, args: &[&str]) -> Result<(), String> {
+    match args {
+        [] => app.toggle_sort_by_priority(),
+        ["priority"] if !app.is_sorted_by_priority() => app.toggle_sort_by_priority(),
+        ["manual"] if app.is_sorted_by_priority() => app.toggle_sort_by_priority(),
+        ["priority" | "manual"] => {}
+        _ => return Err(usage("sort")),
+    }
+    Ok(())
+}
+
+fn filter(app: &mut App, args: &[&str]) -> Result<(), String> {
+    match args {
+        ["due", date @ ..] if !date.is_empty() => {
+            match due::parse_due_date(&date.join(" "), due::today())? {
+                Some(date) => app.filter_by_due(date),
+                None => app.clear_date_filter(),
+            }
+        }
+        ["tag", tags @ ..] if !tags.is_empty() => {
+            let tags = tags
+                .iter()
+                .map(|tag| parse_tag(tag))
+                .collect::<Result<Vec<_>, _>>()?;
+            app.filter_by_tags(tags);
+        }
+        ["clear"] => app.clear_filter(),
+        _ => return Err(usage("filter")),
+    }
+    Ok(())
+}
+
+fn export(app: &mut App, args: &[&str]) -> Result<(), String> {
+    match args {
+        [path] => app.export(path),
+        [] => Err(usage("export")),
+        _ => Err("Too many arguments".to_string()),
+    }
+}
+
+fn tag(app: &mut App, args: &[&str]) -> Result<(), String> {
+    if args.is_empty() {
+        return Err(usage("tag"));
+    }
+    let tags = args
+        .iter()
+        .map(|tag| parse_tag(tag))
+        .collect::<Result<Vec<_>, _>>()?;
+    app.tag_task(&tags)
+}
@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::keymap::{KeyOverrides, KeySpec, Keymap, KeymapMode, DEFAULT_LEADER};
+
+/// Settings read from `config.toml` in the todo-term config directory.
+///
+/// ```toml
+/// [keys]
+/// leader = "<Space>"
+///
+/// [keys.visual]
+/// navigate_down = ["j", "<Down>"]
+/// quit = "<C-q>"
+/// ```
+#[derive(Debug, Default)]
+pub struct Config {
+    pub keymap: Keymap,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    keys: KeysSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeysSection {
+    leader: Option<String>,
+    #[serde(default)]
+    visual: BTreeMap<String, Keys>,
+    #[serde(default)]
+    insert: BTreeMap<String, Keys>,
+    #[serde(default)]
+    calendar: BTreeMap<String, Keys>,
+}
+
+/// One key spec or a list of them.
+#[derive(Debug, Deserialize)]
+#[serde(untagged, expecting = "a key such as \"j\" or a list of keys")]
+enum Keys {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Keys {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            Keys::One(key) => vec![key],
+            Keys::Many(keys) => keys,
+        }
+    }
+}
+
+impl Config {
+    /// Where the config file is looked for: `$XDG_CONFIG_HOME/todo-term`,
+    /// or the platform's config directory.
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("todo-term").join("config.toml"))
+    }
+
+    /// Reads the config file. A missing file gives the defaults, anything
+    /// wrong with an existing one is an error naming the file.
+    pub fn load() -> Result<Config, String> {
+        let Some(path) = Config::path() else {
+            return Ok(Config::default());
+        };
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+        Config::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(contents: &str) -> Result<Config, String> {
+        let file: ConfigFile = toml::from_str(contents).map_err(|e| e.to_string())?;
+
+        let leader = match &file.keys.leader {
+            Some(text) => match KeySpec::parse(text, DEFAULT_LEADER)
+                .map_err(|e| format!("keys.leader: {}", e))?
+                .keys()
+            {
+                [key] => *key,
+                _ => return Err("keys.leader: must be a single key".to_string()),
+            },
+            None => DEFAULT_LEADER,
+        };
+
+        let mut overrides = KeyOverrides::new();
+        for (mode, keys) in [
+            (KeymapMode::Visual, file.keys.visual),
+            (KeymapMode::Insert, file.keys.insert),
+            (KeymapMode::Calendar, file.keys.calendar),
+        ] {
+            let keys = keys
+                .into_iter()
+                .map(|(action, keys)| (action, keys.into_vec()))
+                .collect();
+            overrides.insert(mode, keys);
+        }
+
+        Ok(Config {
+            keymap: Keymap::with_overrides(&overrides, leader)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::Action;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[test]
+    fn binds_the_keys_it_sets() {
+        let config = Config::parse("[keys.visual]\nquit = \"<C-q>\"\n").unwrap();
+        let ctrl_q = KeyEvent::new(KeyCode::Char('q'), KeyModifiers::CONTROL);
+        assert_eq!(config.keymap.lookup(KeymapMode::Visual, ctrl_q), Some(Action::Quit));
+    }
+
+    #[test]
+    fn names_what_is_wrong() {
+        let error = Config::parse("[keys]\nvisual_mode = {}\n").unwrap_err();
+        assert!(error.contains("unknown field `visual_mode`"), "{}", error);
+        let error = Config::parse("[colors]\n").unwrap_err();
+        assert!(error.contains("unknown field `colors`"), "{}", error);
+
+        assert_eq!(
+            Config::parse("[keys.visual]\nfly = \"f\"\n").unwrap_err(),
+            "keys.visual: unknown action `fly`"
+        );
+        assert_eq!(
+            Config::parse("[keys.visual]\nquit = \"<Foo>\"\n").unwrap_err(),
+            "keys.visual.quit: unknown key name `<Foo>`"
+        );
+        assert_eq!(
+            Config::parse("[keys]\nleader = \"ab\"\n").unwrap_err(),
+            "keys.leader: must be a single key"
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use std::path::{Path, PathBuf};
+
+/// The name of the todo file, also looked for in the project directories.
+pub const FILE_NAME: &str = "todo.json";
+
+/// Overrides where the todo file is, unless `--file` is given.
+pub const FILE_ENV: &str = "TODO_TERM_FILE";
+
+/// Where the todo file is, from the first of:
+///
+/// 1. the `--file` flag,
+/// 2. the `TODO_TERM_FILE` environment variable,
+/// 3. a `todo.json` in the current directory or one above it,
+/// 4. `todo.json` in the todo-term data directory, `$XDG_DATA_HOME/todo-term`
+///    or the platform's equivalent.
+///
+/// The path is made absolute so it can be shown as it is.
+pub fn resolve(flag: Option<&str>) -> PathBuf {
+    let env = std::env::var(FILE_ENV).ok();
+    let cwd = std::env::current_dir().ok();
+    let data_dir = dirs::data_dir();
+    let path = resolve_from(flag, env.as_deref(), cwd.as_deref(), data_dir.as_deref());
+    std::path::absolute(&path).unwrap_or(path)
+}
+
+/// [`resolve`] with the environment variable, the current directory and
+/// the platform's data directory given.
+fn resolve_from(
+    flag: Option<&str>,
+    env: Option<&str>,
+    cwd: Option<&Path>,
+    data_dir: Option<&Path>,
+) -> PathBuf {
+    if let Some(path) = flag.or(env.filter(|path| !path.is_empty())) {
+        return PathBuf::from(path);
+    }
+    cwd.and_then(find_project_file)
+        .or_else(|| data_dir.map(|dir| dir.join("todo-term").join(FILE_NAME)))
+        .unwrap_or_else(|| PathBuf::from(FILE_NAME))
+}
+
+/// The closest `todo.json` found walking up from `cwd`.
+fn find_project_file(cwd: &Path) -> Option<PathBuf> {
+    cwd.ancestors()
+        .map(|dir| dir.join(FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// `path` with the home directory shortened to `~`, for showing it.
+pub fn display(path: &Path) -> String {
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(relative) => Path::new("~").join(relative).display().to_string(),
+        None => path.display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A project tree in the system's temp dir, removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> TempDir {
+            let path = std::env::temp_dir()
+                .join(format!("todo-term-test-{:016x}", rand::random::<u64>()));
+            fs::create_dir_all(path.join("project").join("src")).unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn the_flag_comes_before_the_environment() {
+        let data_dir = Path::new("/data");
+        assert_eq!(
+            resolve_from(Some("flag.json"), Some("env.json"), None, Some(data_dir)),
+            PathBuf::from("flag.json")
+        );
+        assert_eq!(
+            resolve_from(None, Some("env.json"), None, Some(data_dir)),
+            PathBuf::from("env.json")
+        );
+    }
+
+    #[test]
+    fn the_environment_comes_before_the_project() {
+        let dir = TempDir::new();
+        let project = dir.0.join("project");
+        fs::write(project.join(FILE_NAME), "").unwrap();
+        let cwd = project.join("src");
+
+        assert_eq!(
+            resolve_from(None, Some("env.json"), Some(&cwd), None),
+            PathBuf::from("env.json")
+        );
+        // an empty variable is not set
+        assert_eq!(
+            resolve_from(None, Some(""), Some(&cwd), None),
+            project.join(FILE_NAME)
+        );
+    }
+
+    #[test]
+    fn the_closest_project_file_comes_before_the_data_dir() {
+        let dir = TempDir::new();
+        let project = dir.0.join("project");
+        fs::write(dir.0.join(FILE_NAME), "").unwrap();
+        fs::write(project.join(FILE_NAME), "").unwrap();
+        // a directory of that name is not a todo file
+        fs::create_dir(project.join("src").join(FILE_NAME)).unwrap();
+
+        let data_dir = Path::new("/data");
+        assert_eq!(
+            resolve_from(None, None, Some(&project.join("src")), Some(data_dir)),
+            project.join(FILE_NAME)
+        );
+    }
+
+    #[test]
+    fn the_data_dir_comes_last() {
+        assert_eq!(
+            resolve_from(None, None, None, Some(Path::new("/data"))),
+            Path::new("/data").join("todo-term").join(FILE_NAME)
+        );
+        assert_eq!(resolve_from(None, None, None, None), PathBuf::from(FILE_NAME));
+    }
+}
@@ -0,0 +1,157 @@
+use time::{macros::format_description, Date, Duration, OffsetDateTime, Weekday};
+
+pub fn today() -> Date {
+    OffsetDateTime::now_utc().date()
+}
+
+pub fn format_date(date: Date) -> String {
+    date.format(format_description!("[year]-[month]-[day]"))
+        .unwrap_or_default()
+}
+
+/// Parses the text typed into the due date prompt.
+///
+/// Accepts `YYYY-MM-DD`, `today`, `tomorrow`, a weekday name (the next
+/// occurrence, never today), and offsets such as `3`, `+3`, `3d` or `2w`.
+/// An empty input clears the due date.
+pub fn parse_due_date(input: &str, today: Date) -> Result<Option<Date>, String> {
+    let input = input.trim().to_lowercase();
+
+    if input.is_empty() {
+        return Ok(None);
+    }
+
+    match input.as_str() {
+        "today" | "tod" => return Ok(Some(today)),
+        "tomorrow" | "tom" => return Ok(today.next_day()),
+        _ => {}
+    }
+
+    if let Some(weekday) = parse_weekday(&input) {
+        let mut date = today.next_day();
+        while let Some(d) = date {
+            if d.weekday() == weekday {
+                return Ok(Some(d));
+            }
+            date = d.next_day();
+        }
+    }
+
+    if let Some((number, multiplier)) = parse_offset(&input) {
+        // bounded first, as a Duration that large can't even be built
+        let span = (Date::MAX - Date::MIN).whole_days();
+        return number
+            .checked_mul(multiplier)
+            .filter(|days| days.abs() <= span)
+            .and_then(|days| today.checked_add(Duration::days(days)))
+            .map(Some)
+            .ok_or_else(|| format!("Date out of range: {}", input));
+    }
+
+    Date::parse(&input, format_description!("[year]-[month]-[day]"))
+        .map(Some)
+        .map_err(|_| format!("Unknown date: {}", input))
+}
+
+/// Describes `due` relative to `today`, e.g. "today", "in 3d", "overdue 2d".
+pub fn relative_label(due: Date, today: Date) -> String {
+    let days = (due - today).whole_days();
+    match days {
+        0 => "today".to_string(),
+        d if d > 0 => format!("in {}d", d),
+        d => format!("overdue {}d", -d),
+    }
+}
+
+/// The number and days per unit of an offset such as `3d` or `+2w`.
+fn parse_offset(input: &str) -> Option<(i64, i64)> {
+    let input = input.strip_prefix('+').unwrap_or(input);
+    let (number, multiplier) = if let Some(n) = input.strip_suffix('w') {
+        (n, 7)
+    } else if let Some(n) = input.strip_suffix('d') {
+        (n, 1)
+    } else {
+        (input, 1)
+    };
+    number.parse::<i64>().ok().map(|n| (n, multiplier))
+}
+
+fn parse_weekday(input: &str) -> Option<Weekday> {
+    const WEEKDAYS: [(&str, Weekday); 7] = [
+        ("monday", Weekday::Monday),
+        ("tuesday", Weekday::Tuesday),
+        ("wednesday", Weekday::Wednesday),
+        ("thursday", Weekday::Thursday),
+        ("friday", Weekday::Friday),
+        ("saturday", Weekday::Saturday),
+        ("sunday", Weekday::Sunday),
+    ];
+
+    if input.len() < 3 {
+        return None;
+    }
+    WEEKDAYS
+        .iter()
+        .find(|(name, _)| name.starts_with(input))
+        .map(|(_, weekday)| *weekday)
+}
+
+/// Moves `date` by whole months, clamping the day to the target month.
+pub fn add_months(date: Date, months: i32) -> Date {
+    let total = date.year() * 12 + (date.month() as i32 - 1) + months;
+    let year = total.div_euclid(12);
+    let month = time::Month::try_from((total.rem_euclid(12) + 1) as u8).unwrap_or(date.month());
+    let day = date.day().min(month.length(year));
+    Date::from_calendar_date(year, month, day).unwrap_or(date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    // a Sunday
+    const TODAY: Date = date!(2026 - 10 - 18);
+
+    fn parse(input: &str) -> Result<Option<Date>, String> {
+        parse_due_date(input, TODAY)
+    }
+
+    #[test]
+    fn parses_dates_names_and_offsets() {
+        assert_eq!(parse(""), Ok(None));
+        assert_eq!(parse("  "), Ok(None));
+        assert_eq!(parse("2026-12-24"), Ok(Some(date!(2026 - 12 - 24))));
+        assert_eq!(parse("Today"), Ok(Some(TODAY)));
+        assert_eq!(parse("tom"), Ok(Some(date!(2026 - 10 - 19))));
+        assert_eq!(parse("3"), Ok(Some(date!(2026 - 10 - 21))));
+        assert_eq!(parse("+3d"), Ok(Some(date!(2026 - 10 - 21))));
+        assert_eq!(parse("2w"), Ok(Some(date!(2026 - 11 - 01))));
+    }
+
+    #[test]
+    fn weekdays_are_never_today() {
+        assert_eq!(parse("sunday"), Ok(Some(date!(2026 - 10 - 25))));
+        assert_eq!(parse("mon"), Ok(Some(date!(2026 - 10 - 19))));
+        assert_eq!(parse("fri"), Ok(Some(date!(2026 - 10 - 23))));
+    }
+
+    #[test]
+    fn rejects_what_isnt_a_date() {
+        assert_eq!(parse("mo"), Err("Unknown date: mo".to_string()));
+        assert_eq!(parse("2026-02-30"), Err("Unknown date: 2026-02-30".to_string()));
+        assert_eq!(parse("someday"), Err("Unknown date: someday".to_string()));
+        assert_eq!(
+            parse("99999999w"),
+            Err("Date out of range: 99999999w".to_string())
+        );
+        assert!(parse(&i64::MAX.to_string()).is_err());
+    }
+
+    #[test]
+    fn labels_are_relative_to_today() {
+        assert_eq!(relative_label(TODAY, TODAY), "today");
+        assert_eq!(relative_label(date!(2026 - 10 - 21), TODAY), "in 3d");
+        assert_eq!(relative_label(date!(2026 - 10 - 16), TODAY), "overdue 2d");
+    }
+}
@@ -0,0 +1,114 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Opens `$VISUAL` or `$EDITOR`, falling back to `vi`, on a temporary file
+/// holding `contents`, and returns the file as the editor left it.
+///
+/// The terminal has to be handed over to the editor before calling this.
+pub fn edit(contents: &str) -> Result<String, String> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    // the editor may come with arguments, such as `code --wait`
+    let mut words = editor.split_whitespace();
+    let program = words.next().ok_or("$EDITOR is empty")?;
+
+    let path = create_temp_file(contents).map_err(|e| format!("Can't write a temp file: {}", e))?;
+
+    let status = Command::new(program).args(words).arg(&path).status();
+    let result = match status {
+        Ok(status) if status.success() => std::fs::read_to_string(&path)
+            .map_err(|e| format!("Can't read {}: {}", path.display(), e)),
+        Ok(status) => Err(format!("{} exited with {}", program, status)),
+        Err(e) => Err(format!("Can't run {}: {}", program, e)),
+    };
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+/// Creates a temp file only we can read, under a name nobody can guess, so
+/// that another user of a shared temp directory can't plant a symlink
+/// there to have the task written or read elsewhere.
+fn create_temp_file(contents: &str) -> io::Result<PathBuf> {
+    loop {
+        let path = std::env::temp_dir().join(format!("todo-term-{:016x}.md", rand::random::<u64>()));
+        let mut options = OpenOptions::new();
+        // never follows a symlink, nor opens a file that is already there
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        match options.open(&path) {
+            Ok(mut file) => {
+                if let Err(e) = file.write_all(contents.as_bytes()) {
+                    let _ = std::fs::remove_file(&path);
+                    return Err(e);
+                }
+                return Ok(path);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// The text a task is edited as: the title on the first line, then a blank
+/// line and the notes.
+pub fn to_text(title: &str, notes: &str) -> String {
+    if notes.is_empty() {
+        format!("{}\n", title)
+    } else {
+        format!("{}\n\n{}\n", title, notes)
+    }
+}
+
+/// Splits edited text back into a title and notes: the first line, and
+/// what follows the blank line after it up to any trailing blank lines.
+/// Returns `None` for a blank file.
+pub fn from_text(text: &str) -> Option<(String, String)> {
+    // editors on Windows may save with CRLF line endings
+    let text = text.replace("\r\n", "\n");
+    if text.trim().is_empty() {
+        return None;
+    }
+    let (title, notes) = text.split_once('\n').unwrap_or((&text, ""));
+    let notes = notes.strip_prefix('\n').unwrap_or(notes).trim_end();
+    Some((title.trim().to_string(), notes.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(title: &str, notes: &str) -> Option<(String, String)> {
+        from_text(&to_text(title, notes))
+    }
+
+    #[test]
+    fn title_and_notes_survive_a_round_trip() {
+        let same = |title: &str, notes: &str| Some((title.to_string(), notes.to_string()));
+        assert_eq!(round_trip("Ship it", ""), same("Ship it", ""));
+        assert_eq!(round_trip("Ship it", "First\n\nThen"), same("Ship it", "First\n\nThen"));
+        assert_eq!(round_trip("", "Only notes"), same("", "Only notes"));
+        assert_eq!(round_trip("Ship it", "\n\nAfter a gap"), same("Ship it", "\n\nAfter a gap"));
+        assert_eq!(round_trip("", ""), None);
+    }
+
+    #[test]
+    fn reads_crlf_line_endings() {
+        assert_eq!(
+            from_text("Ship it\r\n\r\nFirst\r\nThen\r\n"),
+            Some(("Ship it".to_string(), "First\nThen".to_string()))
+        );
+    }
+
+    #[test]
+    fn notes_may_follow_the_title_directly() {
+        assert_eq!(
+            from_text("Ship it\nFirst\n\n\n"),
+            Some(("Ship it".to_string(), "First".to_string()))
+        );
+        assert_eq!(from_text(" \n\n"), None);
+    }
+}
@@ -1,11 +1,14 @@
 use crate::app::AppResult;
+use crate::file_stamp::FileStamp;
 use crossterm::event::{self, Event as CrosstermEvent, KeyEvent, MouseEvent};
-use std::sync::mpsc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 
 /// Terminal events.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum Event {
     /// Terminal tick.
     Tick,
@@ -15,8 +18,15 @@ pub enum Event {
     Mouse(MouseEvent),
     /// Terminal resize.
     Resize(u16, u16),
+    /// Text pasted into the terminal.
+    Paste(String),
+    /// The watched file was written, possibly by todo-term itself.
+    FileChanged,
 }
 
+/// How often the watched file is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
 /// Terminal event handler.
 #[allow(dead_code)]
 #[derive(Debug)]
@@ -27,6 +37,12 @@ pub struct EventHandler {
     receiver: mpsc::Receiver<Event>,
     /// Event handler thread.
     handler: thread::JoinHandle<()>,
+    /// Asks the thread to stop reading the terminal.
+    paused: Arc<AtomicBool>,
+    /// Set by the thread once it no longer reads the terminal.
+    idle: Arc<AtomicBool>,
+    /// The file to send [`Event::FileChanged`] for.
+    watched: Arc<Mutex<Option<PathBuf>>>,
 }
 
 impl EventHandler {
@@ -34,11 +50,27 @@ impl EventHandler {
     pub fn new(tick_rate: u64) -> Self {
         let tick_rate = Duration::from_millis(tick_rate);
         let (sender, receiver) = mpsc::channel();
+        let paused = Arc::new(AtomicBool::new(false));
+        let idle = Arc::new(AtomicBool::new(false));
+        let watched = Arc::new(Mutex::new(None::<PathBuf>));
         let handler = {
             let sender = sender.clone();
+            let paused = paused.clone();
+            let idle = idle.clone();
+            let watched = watched.clone();
             thread::spawn(move || {
                 let mut last_tick = Instant::now();
+                let mut last_watch = Instant::now();
+                // the path being watched and its stamp when last checked
+                let mut seen: Option<(PathBuf, Option<FileStamp>)> = None;
                 loop {
+                    if paused.load(Ordering::SeqCst) {
+                        idle.store(true, Ordering::SeqCst);
+                        thread::sleep(tick_rate);
+                        continue;
+                    }
+                    idle.store(false, Ordering::SeqCst);
+
                     let timeout = tick_rate
                         .checked_sub(last_tick.elapsed())
                         .unwrap_or(tick_rate);
@@ -50,7 +82,7 @@ impl EventHandler {
                             CrosstermEvent::Resize(w, h) => sender.send(Event::Resize(w, h)),
                             CrosstermEvent::FocusGained => Ok(()),
                             CrosstermEvent::FocusLost => Ok(()),
-                            CrosstermEvent::Paste(_) => unimplemented!(),
+                            CrosstermEvent::Paste(text) => sender.send(Event::Paste(text)),
                         }
                         .expect("failed to send terminal event")
                     }
@@ -59,6 +91,24 @@ impl EventHandler {
                         sender.send(Event::Tick).expect("failed to send tick event");
                         last_tick = Instant::now();
                     }
+
+                    if last_watch.elapsed() >= WATCH_INTERVAL {
+                        let path = watched.lock().expect("watched path lock").clone();
+                        let stamp = path.as_deref().and_then(FileStamp::of);
+                        match (&seen, path) {
+                            (Some((seen_path, seen_stamp)), Some(path)) if *seen_path == path => {
+                                if *seen_stamp != stamp {
+                                    sender
+                                        .send(Event::FileChanged)
+                                        .expect("failed to send file event");
+                                }
+                                seen = Some((path, stamp));
+                            }
+                            // a new path starts from how it is now
+                            (_, path) => seen = path.map(|path| (path, stamp)),
+                        }
+                        last_watch = Instant::now();
+                    }
                 }
             })
         };
@@ -66,9 +116,32 @@ impl EventHandler {
             sender,
             receiver,
             handler,
+            paused,
+            idle,
+            watched,
         }
     }
 
+    /// Sends [`Event::FileChanged`] whenever the file at `path` is written,
+    /// instead of for the file watched so far.
+    pub fn watch(&self, path: PathBuf) {
+        *self.watched.lock().expect("watched path lock") = Some(path);
+    }
+
+    /// Stops reading the terminal so another program can, and waits until
+    /// the thread has let go of it.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+        while !self.idle.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// Starts reading the terminal again after [`pause`](EventHandler::pause).
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
     /// Receive the next event from the handler thread.
     ///
     /// This function will always block the current thread if
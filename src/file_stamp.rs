@@ -0,0 +1,21 @@
+use std::path::Path;
+use std::time::SystemTime;
+
+/// The modification time and size of a file, to notice when it was written
+/// without reading it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    /// The stamp of the file at `path`, or `None` if there is no file.
+    pub fn of(path: &Path) -> Option<FileStamp> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(FileStamp {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
@@ -1,14 +1,26 @@
-use crate::{app::{App, AppResult, AppMode}, key_sequencer::KeySequencer};
-use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crate::{
+    app::{App, AppResult, AppMode, Focus, PromptKind},
+    key_sequencer::{KeySequencer, Resolved},
+    keymap::{key_name, Action, Keymap, KeymapMode},
+    hit_map::Target,
+    task_list::TaskMove,
+};
+use crossterm::event::{
+    KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
 
-macro_rules! n_key_press {
+/// AltGr, which Windows reports as control and alt. What it types, like
+/// `@` on many layouts, is text as much as any other character.
+const ALT_GR: KeyModifiers = KeyModifiers::CONTROL.union(KeyModifiers::ALT);
+const ALT_GR_SHIFT: KeyModifiers = ALT_GR.union(KeyModifiers::SHIFT);
+
+macro_rules! i_key_press {
     ($code:pat) => {
         (KeyEvent {
             code: $code,
             kind: KeyEventKind::Press,
-            modifiers: KeyModifiers::NONE,
             ..
-        }, AppMode::Visual)
+        }, AppMode::Insert)
     };
     ($code:pat, $modifiers:pat) => {
         (KeyEvent {
@@ -16,17 +28,17 @@ macro_rules! n_key_press {
             kind: KeyEventKind::Press,
             modifiers: $modifiers,
             ..
-        }, AppMode::Visual)
+        }, AppMode::Insert)
     };
 }
 
-macro_rules! i_key_press {
+macro_rules! p_key_press {
     ($code:pat) => {
         (KeyEvent {
             code: $code,
             kind: KeyEventKind::Press,
             ..
-        }, AppMode::Insert)
+        }, AppMode::Prompt(_))
     };
     ($code:pat, $modifiers:pat) => {
         (KeyEvent {
@@ -34,104 +46,573 @@ macro_rules! i_key_press {
             kind: KeyEventKind::Press,
             modifiers: $modifiers,
             ..
-        }, AppMode::Insert)
+        }, AppMode::Prompt(_))
     };
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub enum KeySequenceEvent {
-    NavigateTop,
+macro_rules! c_key_press {
+    ($code:pat) => {
+        KeyEvent {
+            code: $code,
+            kind: KeyEventKind::Press,
+            modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+            ..
+        }
+    };
+    ($code:pat, $modifiers:pat) => {
+        KeyEvent {
+            code: $code,
+            kind: KeyEventKind::Press,
+            modifiers: $modifiers,
+            ..
+        }
+    };
 }
 
-pub fn setup_key_sequences(key_sequencer: &mut KeySequencer<KeySequenceEvent>) {
-    key_sequencer.register(
-        KeyEvent::from(KeyCode::Char('g')),
-        KeyEvent::from(KeyCode::Char('g')),
-        KeySequenceEvent::NavigateTop,
-    );
+macro_rules! y_key_press {
+    ($code:pat) => {
+        (KeyEvent {
+            code: $code,
+            kind: KeyEventKind::Press,
+            ..
+        }, AppMode::Confirm)
+    };
 }
 
-pub fn handle_normal_mode_sequence_key_events(key_sequence_event: KeySequenceEvent, app: &mut App) -> AppResult<()> {
-    match key_sequence_event{
-        KeySequenceEvent::NavigateTop=> {
-            app.navigate_top();
+macro_rules! t_key_press {
+    ($code:pat) => {
+        (KeyEvent {
+            code: $code,
+            kind: KeyEventKind::Press,
+            ..
+        }, AppMode::TagPicker)
+    };
+}
+
+macro_rules! r_key_press {
+    ($code:pat) => {
+        (KeyEvent {
+            code: $code,
+            kind: KeyEventKind::Press,
+            ..
+        }, AppMode::Recover)
+    };
+}
+
+pub fn setup_key_sequences(key_sequencer: &mut KeySequencer<Action>, keymap: &Keymap) {
+    for (keys, action) in keymap.sequences() {
+        key_sequencer.register(&keys, action);
+    }
+}
+
+/// Handles a key of the list in visual mode, where keys can form sequences.
+pub fn handle_key_sequence_events(
+    key_event: KeyEvent,
+    key_sequencer: &mut KeySequencer<Action>,
+    app: &mut App,
+) -> AppResult<()> {
+    let resolved = key_sequencer.feed(key_event);
+    handle_resolved_keys(resolved, key_sequencer, app)
+}
+
+/// Resolves a sequence left pending once nothing follows it in time.
+pub fn handle_key_sequence_timeout(
+    key_sequencer: &mut KeySequencer<Action>,
+    app: &mut App,
+) -> AppResult<()> {
+    let resolved = key_sequencer.tick();
+    handle_resolved_keys(resolved, key_sequencer, app)
+}
+
+fn handle_resolved_keys(
+    resolved: Vec<Resolved<Action>>,
+    key_sequencer: &KeySequencer<Action>,
+    app: &mut App,
+) -> AppResult<()> {
+    for step in resolved {
+        match step {
+            Resolved::Sequence(action, count) => run_action(action, count, app)?,
+            Resolved::Key(key_event) => handle_key_events(key_event, app)?,
         }
     }
+
+    let count = key_sequencer.count().map(|count| count.to_string());
+    let pending = count
+        .into_iter()
+        .chain(key_sequencer.pending().iter().map(|key| key_name(*key)))
+        .collect::<String>();
+    app.set_pending_keys(pending);
     Ok(())
 }
 
-pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
-    match (key_event, app.get_mode()) {
-        n_key_press!(KeyCode::Char('c') | KeyCode::Char('C'), KeyModifiers::CONTROL) => {
-            app.quit();
+/// Does what a key bound in the keymap asks for. A count repeats motions
+/// and the actions on tasks; `5gg` and `5G` go to row 5. Other actions
+/// ignore it.
+fn run_action(action: Action, count: Option<usize>, app: &mut App) -> AppResult<()> {
+    let times = count.unwrap_or(1);
+    if app.get_mode() == AppMode::VisualLine && !acts_on_selection(action) {
+        app.exit_visual_line();
+    }
+    match action {
+        Action::Quit if app.get_mode() == AppMode::VisualLine => {
+            app.exit_visual_line();
         }
-        n_key_press!(KeyCode::Esc | KeyCode::Char('q')) => {
+        Action::Quit => {
             app.quit();
         }
-        n_key_press!(KeyCode::Char('j')) => {
-            app.navigate_down();
+        Action::ToggleHelp => {
+            app.toggle_help();
         }
-        n_key_press!(KeyCode::Char('k')) => {
-            app.navigate_up();
+        Action::ToggleFocus => {
+            app.toggle_focus();
         }
-        n_key_press!(KeyCode::Char('h')) => {
-            app.toggle_help();
+        Action::NavigateDown => {
+            app.navigate_down_by(times);
         }
-        n_key_press!(KeyCode::Char('o')) => {
-            app.add_task_below();
+        Action::NavigateUp => {
+            app.navigate_up_by(times);
+        }
+        Action::NavigateTop | Action::NavigateBottom if count.is_some() => {
+            app.navigate_to_row(times - 1);
+        }
+        Action::NavigateTop => {
+            app.navigate_top();
+        }
+        Action::NavigateBottom => {
+            app.navigate_bottom();
+        }
+        Action::AddTaskBelow => {
             app.enter_insert_mode();
+            app.add_task_below();
         }
-        n_key_press!(KeyCode::Char('O'), KeyModifiers::SHIFT) => {
-            app.add_task_above();
+        Action::AddTaskAbove => {
             app.enter_insert_mode();
+            app.add_task_above();
         }
-        n_key_press!(KeyCode::Char('x')) => {
-            app.toggle_task();
+        Action::ToggleTask => {
+            app.toggle_tasks(times);
             app.save();
         }
-        n_key_press!(KeyCode::Char('G'), KeyModifiers::SHIFT) => {
-            app.navigate_bottom();
-        }
-        n_key_press!(KeyCode::Char('d')) => {
-            app.delete_task();
+        Action::DeleteTask => {
+            app.delete_tasks(times);
             app.save();
         }
-        n_key_press!(KeyCode::Char('c')) => {
-            app.reset_task();
+        Action::ChangeTask => {
             app.enter_insert_mode();
+            app.reset_task();
         }
-        n_key_press!(KeyCode::Char('a')) => {
+        Action::AppendToTask => {
             app.enter_insert_mode();
         }
-        n_key_press!(KeyCode::Char('y')) => {
-            app.yank_task();
+        Action::EditNotes => {
+            app.request_editor();
+        }
+        Action::VisualLine if app.get_mode() == AppMode::VisualLine => {
+            app.exit_visual_line();
         }
-        n_key_press!(KeyCode::Char('p')) => {
-            app.paste_task_below();
+        Action::VisualLine => {
+            app.enter_visual_line();
+        }
+        Action::YankTask => {
+            app.yank_tasks(times);
+        }
+        Action::PasteBelow => {
+            app.paste_tasks_below(times);
             app.save();
         }
-        n_key_press!(KeyCode::Char('P'), KeyModifiers::SHIFT) => {
-            app.paste_task_above();
+        Action::PasteAbove => {
+            app.paste_tasks_above(times);
             app.save();
         }
-        i_key_press!(KeyCode::Char('c') | KeyCode::Char('C'), KeyModifiers::CONTROL) => {
-            app.exit_insert_mode();
+        Action::SetDueDate => {
+            app.enter_due_date_prompt();
+        }
+        Action::RaisePriority => {
+            app.raise_priority();
             app.save();
         }
-        i_key_press!(KeyCode::Esc) => {
-            app.exit_insert_mode();
+        Action::LowerPriority => {
+            app.lower_priority();
+            app.save();
+        }
+        Action::SortByPriority => {
+            app.toggle_sort_by_priority();
             app.save();
         }
-        i_key_press!(KeyCode::Char(c)) => {
-            app.append_to_task(c);
+        Action::Indent => {
+            app.indent_task_by(times);
+            app.save();
         }
-        i_key_press!(KeyCode::Backspace) => {
-            app.pop_from_task();
+        Action::Outdent => {
+            app.outdent_task_by(times);
+            app.save();
         }
-        i_key_press!(KeyCode::Enter) => {
+        Action::MoveDown => {
+            app.move_task(TaskMove::Down, times);
+            app.save();
+        }
+        Action::MoveUp => {
+            app.move_task(TaskMove::Up, times);
+            app.save();
+        }
+        Action::MoveToTop => {
+            app.move_task(TaskMove::Top, 1);
+            app.save();
+        }
+        Action::MoveToBottom => {
+            app.move_task(TaskMove::Bottom, 1);
+            app.save();
+        }
+        Action::ToggleFold => {
+            app.toggle_fold();
+            app.save();
+        }
+        Action::FoldAll => {
+            app.set_all_folded(true);
+            app.save();
+        }
+        Action::UnfoldAll => {
+            app.set_all_folded(false);
+            app.save();
+        }
+        Action::NextList => {
+            app.next_list();
+            app.save();
+        }
+        Action::PreviousList => {
+            app.previous_list();
+            app.save();
+        }
+        Action::NewList => {
+            app.enter_new_list_prompt();
+        }
+        Action::RenameList => {
+            app.enter_rename_list_prompt();
+        }
+        Action::DeleteList => {
+            app.request_delete_list();
+        }
+        Action::MoveToList => {
+            app.enter_move_task_prompt();
+        }
+        Action::TagPicker => {
+            app.enter_tag_picker();
+        }
+        Action::Search => {
+            app.enter_search_prompt();
+        }
+        Action::SearchNext => {
+            app.search_next();
+        }
+        Action::SearchPrevious => {
+            app.search_previous();
+        }
+        Action::CommandLine => {
+            app.enter_command_mode();
+        }
+        Action::Undo => {
+            app.undo();
+            app.save();
+        }
+        Action::Redo => {
+            app.redo();
+            app.save();
+        }
+        Action::ExitInsert => {
+            app.exit_insert_mode();
+            app.save();
+        }
+        Action::NextTask => {
+            app.finish_task_edit();
             app.save();
             app.add_task_below();
         }
+        Action::CursorLeft => {
+            app.edit_task(|editor| editor.move_left());
+        }
+        Action::CursorRight => {
+            app.edit_task(|editor| editor.move_right());
+        }
+        Action::CursorHome => {
+            app.edit_task(|editor| editor.move_home());
+        }
+        Action::CursorEnd => {
+            app.edit_task(|editor| editor.move_end());
+        }
+        Action::WordLeft => {
+            app.edit_task(|editor| editor.move_word_left());
+        }
+        Action::WordRight => {
+            app.edit_task(|editor| editor.move_word_right());
+        }
+        Action::DeleteBackward => {
+            app.edit_task(|editor| editor.delete_backward());
+        }
+        Action::DeleteForward => {
+            app.edit_task(|editor| editor.delete_forward());
+        }
+        Action::DeleteWordBackward => {
+            app.edit_task(|editor| editor.delete_word_backward());
+        }
+        Action::DeleteToStart => {
+            app.edit_task(|editor| editor.delete_to_start());
+        }
+        Action::FocusList => {
+            app.focus_list();
+        }
+        Action::DayBack => {
+            app.calendar_move_days(-1);
+        }
+        Action::DayForward => {
+            app.calendar_move_days(1);
+        }
+        Action::WeekBack => {
+            app.calendar_move_days(-7);
+        }
+        Action::WeekForward => {
+            app.calendar_move_days(7);
+        }
+        Action::MonthBack => {
+            app.calendar_move_months(-1);
+        }
+        Action::MonthForward => {
+            app.calendar_move_months(1);
+        }
+        Action::Today => {
+            app.calendar_today();
+        }
+        Action::FilterDay => {
+            app.filter_by_calendar_date();
+        }
+        Action::ClearDayFilter => {
+            app.clear_date_filter();
+        }
+    }
+    Ok(())
+}
+
+/// Handles text pasted into the terminal. In insert mode, the prompts and
+/// the command line it is typed in, with line breaks as spaces. On the list
+/// every line becomes a new task.
+pub fn handle_paste_events(text: String, app: &mut App) -> AppResult<()> {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    match (app.get_mode(), app.get_focus()) {
+        (AppMode::Insert, _) => {
+            app.edit_task(|editor| editor.insert_str(&line));
+        }
+        (AppMode::Prompt(_), _) => {
+            line.chars().for_each(|c| app.push_to_prompt(c));
+        }
+        (AppMode::Command, _) => {
+            line.chars().for_each(|c| app.push_to_command(c));
+        }
+        (AppMode::Visual | AppMode::VisualLine, Focus::List) => {
+            app.exit_visual_line();
+            app.add_tasks_from_text(&text);
+            app.save();
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Handles the mouse in visual mode: clicks select rows and calendar days, a
+/// click on a bullet toggles its task, a double click edits it and the
+/// wheel moves through the list or the months.
+pub fn handle_mouse_events(mouse_event: MouseEvent, app: &mut App) -> AppResult<()> {
+    if !matches!(app.get_mode(), AppMode::Visual | AppMode::VisualLine) || app.is_help_visible() {
+        return Ok(());
+    }
+
+    let target = app
+        .get_hit_map()
+        .target(mouse_event.column, mouse_event.row);
+    match (mouse_event.kind, target) {
+        (MouseEventKind::Down(MouseButton::Left), Target::Bullet(row)) => {
+            app.exit_visual_line();
+            app.click_row(row);
+            app.toggle_tasks(1);
+            app.save();
+        }
+        (MouseEventKind::Down(MouseButton::Left), Target::Row(row)) => {
+            let double_click = app.click_row(row);
+            if double_click {
+                app.exit_visual_line();
+                app.enter_insert_mode();
+            }
+        }
+        (MouseEventKind::Down(MouseButton::Left), Target::List) => {
+            app.focus_list();
+        }
+        (MouseEventKind::Down(MouseButton::Left), Target::Day(date)) => {
+            app.exit_visual_line();
+            app.focus_calendar_date(date);
+        }
+        (MouseEventKind::ScrollDown, Target::Row(_) | Target::Bullet(_) | Target::List) => {
+            app.scroll_list(true);
+        }
+        (MouseEventKind::ScrollUp, Target::Row(_) | Target::Bullet(_) | Target::List) => {
+            app.scroll_list(false);
+        }
+        (MouseEventKind::ScrollDown, Target::Day(_) | Target::Calendar) => {
+            app.calendar_move_months(1);
+        }
+        (MouseEventKind::ScrollUp, Target::Day(_) | Target::Calendar) => {
+            app.calendar_move_months(-1);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Whether `action` keeps a visual line selection going: it moves the
+/// cursor, acts on the selected tasks or ends the selection. Other actions
+/// end the selection first and apply to the selected row.
+fn acts_on_selection(action: Action) -> bool {
+    matches!(
+        action,
+        Action::Quit
+            | Action::ToggleHelp
+            | Action::NavigateDown
+            | Action::NavigateUp
+            | Action::NavigateTop
+            | Action::NavigateBottom
+            | Action::SearchNext
+            | Action::SearchPrevious
+            | Action::VisualLine
+            | Action::ToggleTask
+            | Action::DeleteTask
+            | Action::YankTask
+            | Action::PasteBelow
+            | Action::PasteAbove
+            | Action::RaisePriority
+            | Action::LowerPriority
+            | Action::MoveToList
+            | Action::CommandLine
+    )
+}
+
+/// Keys of the `:` command line. What the commands do lives in the
+/// command registry.
+fn handle_command_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
+    match key_event {
+        c_key_press!(KeyCode::Char('c') | KeyCode::Char('C'), KeyModifiers::CONTROL) => {
+            app.cancel_command();
+        }
+        c_key_press!(KeyCode::Esc) => {
+            app.cancel_command();
+        }
+        c_key_press!(KeyCode::Enter) => {
+            app.submit_command();
+            app.save();
+        }
+        c_key_press!(KeyCode::Tab) => {
+            app.complete_command(true);
+        }
+        c_key_press!(KeyCode::BackTab) => {
+            app.complete_command(false);
+        }
+        c_key_press!(KeyCode::Up) => {
+            app.command_history_previous();
+        }
+        c_key_press!(KeyCode::Down) => {
+            app.command_history_next();
+        }
+        c_key_press!(KeyCode::Backspace) => {
+            app.pop_from_command();
+        }
+        c_key_press!(KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT | ALT_GR | ALT_GR_SHIFT) => {
+            app.push_to_command(c);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
+    let keymap_mode = match (app.get_mode(), app.get_focus()) {
+        (AppMode::Visual, Focus::List) | (AppMode::VisualLine, _) => Some(KeymapMode::Visual),
+        (AppMode::Visual, Focus::Calendar) => Some(KeymapMode::Calendar),
+        (AppMode::Insert, _) => Some(KeymapMode::Insert),
+        _ => None,
+    };
+    if let (Some(mode), KeyEventKind::Press) = (keymap_mode, key_event.kind) {
+        if let Some(action) = app.get_keymap().lookup(mode, key_event) {
+            return run_action(action, None, app);
+        }
+    }
+    if app.get_mode() == AppMode::Command {
+        return handle_command_key_events(key_event, app);
+    }
+
+    match (key_event, app.get_mode()) {
+        i_key_press!(KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT | ALT_GR | ALT_GR_SHIFT) => {
+            app.edit_task(|editor| editor.insert(c));
+        }
+        y_key_press!(KeyCode::Char('y') | KeyCode::Char('Y')) => {
+            app.answer_confirm(true);
+            app.save();
+        }
+        y_key_press!(KeyCode::Char('n') | KeyCode::Char('N')) => {
+            app.answer_confirm(false);
+            app.save();
+        }
+        y_key_press!(KeyCode::Esc) => {
+            app.cancel_confirm();
+        }
+        t_key_press!(KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('#')) => {
+            app.exit_tag_picker();
+        }
+        t_key_press!(KeyCode::Char('j') | KeyCode::Down) => {
+            app.tag_picker_down();
+        }
+        t_key_press!(KeyCode::Char('k') | KeyCode::Up) => {
+            app.tag_picker_up();
+        }
+        t_key_press!(KeyCode::Char(' ') | KeyCode::Enter) => {
+            app.toggle_picked_tag();
+        }
+        t_key_press!(KeyCode::Char('c') | KeyCode::Backspace) => {
+            app.clear_tag_filter();
+        }
+        r_key_press!(KeyCode::Esc | KeyCode::Char('q')) => {
+            app.exit_backup_picker();
+        }
+        r_key_press!(KeyCode::Char('j') | KeyCode::Down) => {
+            app.backup_picker_down();
+        }
+        r_key_press!(KeyCode::Char('k') | KeyCode::Up) => {
+            app.backup_picker_up();
+        }
+        r_key_press!(KeyCode::Enter) => {
+            app.restore_picked_backup();
+        }
+        p_key_press!(KeyCode::Esc) => {
+            app.cancel_prompt();
+        }
+        p_key_press!(KeyCode::Enter) => {
+            app.submit_prompt();
+            app.save();
+        }
+        (KeyEvent {
+            code: KeyCode::Char('r') | KeyCode::Char('R'),
+            kind: KeyEventKind::Press,
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        }, AppMode::Prompt(PromptKind::Search)) => {
+            app.toggle_search_regex();
+        }
+        p_key_press!(KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT | ALT_GR | ALT_GR_SHIFT) => {
+            app.push_to_prompt(c);
+        }
+        p_key_press!(KeyCode::Backspace) => {
+            app.pop_from_prompt();
+        }
         _ => {}
     }
     Ok(())
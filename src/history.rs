@@ -0,0 +1,61 @@
+/// Bounded undo/redo stacks of snapshots.
+///
+/// Callers [`record`](History::record) the state *before* a change and hand
+/// over the current state when undoing or redoing, so the stacks never hold
+/// the live state.
+pub struct History<T> {
+    undo_stack: Vec<T>,
+    redo_stack: Vec<T>,
+    /// The redo stack the last [`record`](History::record) cleared, back
+    /// in place if that step is discarded.
+    cleared_redo_stack: Vec<T>,
+    limit: usize,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        Self {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            cleared_redo_stack: Vec::new(),
+            limit: 100,
+        }
+    }
+}
+
+impl<T: PartialEq> History<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, state: T) {
+        self.cleared_redo_stack = std::mem::take(&mut self.redo_stack);
+        self.undo_stack.push(state);
+        if self.undo_stack.len() > self.limit {
+            self.undo_stack.remove(0);
+        }
+    }
+
+    /// Drops the last recorded step if nothing changed since it was taken,
+    /// and with it brings back what could be redone before.
+    pub fn discard_if_unchanged(&mut self, current: &T) {
+        if self.undo_stack.last() == Some(current) {
+            self.undo_stack.pop();
+            self.redo_stack = std::mem::take(&mut self.cleared_redo_stack);
+        }
+    }
+
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let state = self.undo_stack.pop()?;
+        self.cleared_redo_stack.clear();
+        self.redo_stack.push(current);
+        Some(state)
+    }
+
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let state = self.redo_stack.pop()?;
+        self.cleared_redo_stack.clear();
+        self.undo_stack.push(current);
+        Some(state)
+    }
+}
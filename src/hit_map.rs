@@ -0,0 +1,85 @@
+use ratatui::layout::Rect;
+use time::Date;
+
+/// Where the last frame drew the things that can be clicked, so a mouse
+/// position can be mapped back to them. Filled in by [`ui::render`].
+///
+/// [`ui::render`]: crate::ui::render
+#[derive(Debug, Default)]
+pub struct HitMap {
+    list: Rect,
+    rows: Vec<RowArea>,
+    calendar: Rect,
+    days: Vec<(Rect, Date)>,
+}
+
+/// A visible row of the list and its `[ ]` bullet.
+#[derive(Debug)]
+struct RowArea {
+    row: usize,
+    area: Rect,
+    bullet: Rect,
+}
+
+/// What is under the mouse.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Target {
+    /// The bullet of a row of the list.
+    Bullet(usize),
+    /// A row of the list anywhere but its bullet.
+    Row(usize),
+    /// The list, below its rows.
+    List,
+    Day(Date),
+    /// The calendar, outside its days.
+    Calendar,
+    None,
+}
+
+impl HitMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_list(&mut self, area: Rect) {
+        self.list = area;
+        self.rows.clear();
+    }
+
+    pub fn add_row(&mut self, row: usize, area: Rect, bullet: Rect) {
+        self.rows.push(RowArea { row, area, bullet });
+    }
+
+    pub fn set_calendar(&mut self, area: Rect) {
+        self.calendar = area;
+        self.days.clear();
+    }
+
+    pub fn add_day(&mut self, area: Rect, date: Date) {
+        self.days.push((area, date));
+    }
+
+    pub fn target(&self, column: u16, row: u16) -> Target {
+        let hit = |area: &Rect| {
+            area.x <= column && column < area.right() && area.y <= row && row < area.bottom()
+        };
+
+        if let Some(row) = self.rows.iter().find(|r| hit(&r.area)) {
+            return if hit(&row.bullet) {
+                Target::Bullet(row.row)
+            } else {
+                Target::Row(row.row)
+            };
+        }
+        if let Some((_, date)) = self.days.iter().find(|(area, _)| hit(area)) {
+            return Target::Day(*date);
+        }
+        if hit(&self.list) {
+            Target::List
+        } else if hit(&self.calendar) {
+            Target::Calendar
+        } else {
+            Target::None
+        }
+    }
+}
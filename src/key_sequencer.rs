@@ -1,6 +1,11 @@
 use std::collections::HashMap;
 
-use crossterm::event::{KeyEvent, KeyEventKind};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+use crate::keymap::normalize;
+
+/// Counts are capped so repeating an action can't hang the app.
+const MAX_COUNT: usize = 9999;
 
 trait KeySequencerFilter {
     fn filter(&self, key_event: KeyEvent) -> Option<KeyEvent>;
@@ -14,25 +19,68 @@ impl KeySequencerFilter for DefaultKeySequencerFilter {
             KeyEvent {
                 kind: KeyEventKind::Press,
                 ..
-            } => Some(key_event),
+            } => Some(normalize(key_event)),
             _ => None,
         }
     }
 }
 
-pub struct KeySequencer<T: Clone> {
-    sequences: HashMap<(KeyEvent, KeyEvent), T>,
-    last_key: Option<KeyEvent>,
+/// What the keys fed to a [`KeySequencer`] turned out to be.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Resolved<T> {
+    /// A registered sequence was completed, with the count typed before it.
+    Sequence(T, Option<usize>),
+    /// A key that is not part of any sequence, to be handled on its own.
+    Key(KeyEvent),
+}
+
+/// A node of the sequence trie. A node can both complete a sequence and
+/// start longer ones, like `g` next to `gg`.
+struct Node<T> {
+    value: Option<T>,
+    children: HashMap<KeyEvent, Node<T>>,
+}
+
+impl<T> Default for Node<T> {
+    fn default() -> Self {
+        Self {
+            value: None,
+            children: HashMap::new(),
+        }
+    }
+}
+
+/// Matches typed keys against registered key sequences of any length.
+///
+/// Keys that could still continue a sequence are held back as pending. If a
+/// pending prefix is a complete sequence too, it is resolved once no further
+/// key arrives before the timeout. A prefix whose longer sequences all do
+/// the same as it, like `d` next to `dd`, is resolved at once instead, and
+/// the rest of such a sequence typed before the timeout is dropped. It only
+/// waits after a count, so that `3dd` is one sequence.
+///
+/// Digits typed before a sequence are collected as its count, as in `5j`.
+/// `0` only continues a count, and a digit that starts a registered sequence
+/// is not taken as one. `Esc` drops the count and pending keys.
+pub struct KeySequencer<T: Clone + PartialEq> {
+    root: Node<T>,
+    pending: Vec<KeyEvent>,
+    /// The pending keys were resolved already, as a prefix that repeats its
+    /// longer sequences.
+    resolved_early: bool,
+    count: Option<usize>,
     tick_count: u32,
     reset_tick_count: u32,
     filter: Box<dyn KeySequencerFilter>,
 }
 
-impl<T: Clone> Default for KeySequencer<T> {
+impl<T: Clone + PartialEq> Default for KeySequencer<T> {
     fn default() -> Self {
         Self {
-            sequences: HashMap::new(),
-            last_key: None,
+            root: Node::default(),
+            pending: Vec::new(),
+            resolved_early: false,
+            count: None,
             tick_count: 0,
             reset_tick_count: 10,
             filter: Box::new(DefaultKeySequencerFilter),
@@ -40,39 +88,305 @@ impl<T: Clone> Default for KeySequencer<T> {
     }
 }
 
-impl<T: Clone> KeySequencer<T> {
+impl<T: Clone + PartialEq> KeySequencer<T> {
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub fn tick(&mut self) {
+    /// Resolves the pending keys once the timeout runs out.
+    pub fn tick(&mut self) -> Vec<Resolved<T>> {
+        if self.pending.is_empty() {
+            return Vec::new();
+        }
+
         self.tick_count += 1;
-        if self.tick_count >= self.reset_tick_count {
-            self.last_key = None;
-            self.tick_count = 0;
+        if self.tick_count < self.reset_tick_count {
+            return Vec::new();
+        }
+        self.tick_count = 0;
+        let mut resolved = Vec::new();
+        if std::mem::take(&mut self.resolved_early) {
+            self.pending.clear();
+        }
+        while !self.pending.is_empty() {
+            self.resolve_front(&mut resolved);
         }
+        resolved
     }
 
-    pub fn feed(&mut self, key_event: KeyEvent) -> Option<T> {
-        if let None = self.filter.filter(key_event) {
-            return None;
+    /// Feeds a key. Returns nothing while the keys so far may still become a
+    /// longer sequence.
+    pub fn feed(&mut self, key_event: KeyEvent) -> Vec<Resolved<T>> {
+        if self.filter.filter(key_event).is_none() {
+            return vec![Resolved::Key(key_event)];
         }
 
         self.tick_count = 0;
-        let last_key = self.last_key;
-        self.last_key = Some(key_event);
+        if self.pending.is_empty() {
+            if let Some(digit) = self.count_digit(key_event) {
+                let count = self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit);
+                self.count = Some(count.min(MAX_COUNT));
+                return Vec::new();
+            }
+        }
+        if key_event.code == KeyCode::Esc && (self.count.is_some() || !self.pending.is_empty()) {
+            self.count = None;
+            self.pending.clear();
+            self.resolved_early = false;
+            return Vec::new();
+        }
 
-        if let Some(last_key) = last_key {
-            if let Some(event) = self.sequences.get(&(last_key, key_event)) {
-                self.last_key = None;
-                return Some(event.clone());
+        self.pending.push(key_event);
+        let mut resolved = Vec::new();
+        while !self.pending.is_empty() {
+            match self.find(&self.pending) {
+                // more keys may follow
+                Some(node) if !node.children.is_empty() => {
+                    if !self.resolved_early && self.count.is_none() && repeats_itself(node) {
+                        let value = node.value.clone().expect("checked by repeats_itself");
+                        resolved.push(Resolved::Sequence(value, None));
+                        self.resolved_early = true;
+                    }
+                    break;
+                }
+                Some(node) => {
+                    let value = node.value.clone().expect("leaf nodes hold a sequence");
+                    if !std::mem::take(&mut self.resolved_early) {
+                        resolved.push(Resolved::Sequence(value, self.count.take()));
+                    }
+                    self.pending.clear();
+                }
+                // the key after a prefix resolved early starts afresh
+                None if self.resolved_early => {
+                    self.resolved_early = false;
+                    self.pending.drain(..self.pending.len() - 1);
+                }
+                None => self.resolve_front(&mut resolved),
             }
         }
+        resolved
+    }
+
+    /// The keys typed so far of a sequence in progress.
+    pub fn pending(&self) -> &[KeyEvent] {
+        &self.pending
+    }
+
+    /// The count typed so far, if any.
+    pub fn count(&self) -> Option<usize> {
+        self.count
+    }
+
+    pub fn register(&mut self, keys: &[KeyEvent], event: T) {
+        let mut node = &mut self.root;
+        for key in keys {
+            node = node.children.entry(normalize(*key)).or_default();
+        }
+        node.value = Some(event);
+    }
+
+    /// The value of `key_event` if it is a digit of a count.
+    fn count_digit(&self, key_event: KeyEvent) -> Option<usize> {
+        let KeyCode::Char(c) = key_event.code else {
+            return None;
+        };
+        let digit = c.to_digit(10)? as usize;
+        if key_event.modifiers != KeyModifiers::NONE || (digit == 0 && self.count.is_none()) {
+            return None;
+        }
+        if self.count.is_none() && self.root.children.contains_key(&normalize(key_event)) {
+            return None;
+        }
+        Some(digit)
+    }
 
-        None
+    fn find(&self, keys: &[KeyEvent]) -> Option<&Node<T>> {
+        let mut node = &self.root;
+        for key in keys {
+            node = node.children.get(&normalize(*key))?;
+        }
+        Some(node)
     }
 
-    pub fn register(&mut self, key1: KeyEvent, key2: KeyEvent, event: T) {
-        self.sequences.insert((key1, key2), event);
+    /// Takes the longest complete sequence off the front of the pending
+    /// keys, or the first key on its own if none starts there.
+    fn resolve_front(&mut self, resolved: &mut Vec<Resolved<T>>) {
+        for len in (1..=self.pending.len()).rev() {
+            if let Some(value) = self.find(&self.pending[..len]).and_then(|node| node.value.clone()) {
+                resolved.push(Resolved::Sequence(value, self.count.take()));
+                self.pending.drain(..len);
+                return;
+            }
+        }
+        // a count only applies to sequences
+        self.count = None;
+        resolved.push(Resolved::Key(self.pending.remove(0)));
+    }
+}
+
+/// Whether `node` is a sequence that every longer one starting with it
+/// only repeats, like `d` next to `dd`.
+fn repeats_itself<T: PartialEq>(node: &Node<T>) -> bool {
+    node.value.is_some()
+        && node
+            .children
+            .values()
+            .all(|child| child.value == node.value && child.children.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::{Action, KeySpec, Keymap, DEFAULT_LEADER};
+
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    fn sequencer(bindings: &[(&str, &'static str)]) -> KeySequencer<&'static str> {
+        let mut sequencer = KeySequencer::new();
+        for (spec, name) in bindings {
+            let spec = KeySpec::parse(spec, DEFAULT_LEADER).unwrap();
+            sequencer.register(spec.keys(), *name);
+        }
+        sequencer
+    }
+
+    fn feed<T: Clone + PartialEq>(sequencer: &mut KeySequencer<T>, keys: &str) -> Vec<Resolved<T>> {
+        keys.chars().flat_map(|c| sequencer.feed(key(c))).collect()
+    }
+
+    fn timeout<T: Clone + PartialEq>(sequencer: &mut KeySequencer<T>) -> Vec<Resolved<T>> {
+        (0..sequencer.reset_tick_count)
+            .flat_map(|_| sequencer.tick())
+            .collect()
+    }
+
+    #[test]
+    fn ambiguous_prefix_resolves_on_timeout() {
+        let mut sequencer = sequencer(&[("g", "g"), ("gg", "gg")]);
+
+        assert_eq!(feed(&mut sequencer, "g"), vec![]);
+        assert_eq!(sequencer.pending(), &[key('g')]);
+        assert_eq!(timeout(&mut sequencer), vec![Resolved::Sequence("g", None)]);
+        assert!(sequencer.pending().is_empty());
+    }
+
+    #[test]
+    fn ambiguous_prefix_continues_into_longer_sequence() {
+        let mut sequencer = sequencer(&[("g", "g"), ("gg", "gg")]);
+
+        assert_eq!(feed(&mut sequencer, "gg"), vec![Resolved::Sequence("gg", None)]);
+        assert_eq!(timeout(&mut sequencer), vec![]);
+    }
+
+    #[test]
+    fn unmatched_prefix_falls_back_to_single_keys() {
+        let mut sequencer = sequencer(&[("gg", "gg"), ("x", "x")]);
+
+        assert_eq!(
+            feed(&mut sequencer, "gx"),
+            vec![Resolved::Key(key('g')), Resolved::Sequence("x", None)]
+        );
+        assert_eq!(
+            feed(&mut sequencer, "gq"),
+            vec![Resolved::Key(key('g')), Resolved::Key(key('q'))]
+        );
+    }
+
+    #[test]
+    fn unfinished_sequence_without_value_times_out_as_keys() {
+        let mut sequencer = sequencer(&[("zM", "fold")]);
+
+        assert_eq!(feed(&mut sequencer, "z"), vec![]);
+        assert_eq!(timeout(&mut sequencer), vec![Resolved::Key(key('z'))]);
+    }
+
+    #[test]
+    fn leader_expands_to_the_leader_key() {
+        let mut sequencer = sequencer(&[("<leader>tn", "new")]);
+        assert_eq!(feed(&mut sequencer, "\\tn"), vec![Resolved::Sequence("new", None)]);
+
+        let mut sequencer = KeySequencer::new();
+        let spec = KeySpec::parse("<leader>tn", key(' ')).unwrap();
+        sequencer.register(spec.keys(), "new");
+        assert_eq!(feed(&mut sequencer, " tn"), vec![Resolved::Sequence("new", None)]);
+    }
+
+    #[test]
+    fn count_applies_to_the_next_sequence() {
+        let mut sequencer = sequencer(&[("j", "down"), ("gg", "top")]);
+
+        assert_eq!(feed(&mut sequencer, "5"), vec![]);
+        assert_eq!(sequencer.count(), Some(5));
+        assert_eq!(feed(&mut sequencer, "j"), vec![Resolved::Sequence("down", Some(5))]);
+        assert_eq!(feed(&mut sequencer, "12gg"), vec![Resolved::Sequence("top", Some(12))]);
+        assert_eq!(sequencer.count(), None);
+    }
+
+    #[test]
+    fn zero_only_continues_a_count() {
+        let mut sequencer = sequencer(&[("j", "down")]);
+
+        assert_eq!(feed(&mut sequencer, "0"), vec![Resolved::Key(key('0'))]);
+        assert_eq!(feed(&mut sequencer, "10j"), vec![Resolved::Sequence("down", Some(10))]);
+    }
+
+    #[test]
+    fn count_is_dropped_by_esc_and_by_unbound_keys() {
+        let mut sequencer = sequencer(&[("j", "down")]);
+
+        feed(&mut sequencer, "5");
+        assert_eq!(sequencer.feed(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)), vec![]);
+        assert_eq!(feed(&mut sequencer, "j"), vec![Resolved::Sequence("down", None)]);
+
+        assert_eq!(feed(&mut sequencer, "5q"), vec![Resolved::Key(key('q'))]);
+        assert_eq!(sequencer.count(), None);
+    }
+
+    #[test]
+    fn count_is_capped() {
+        let mut sequencer = sequencer(&[("j", "down")]);
+
+        assert_eq!(
+            feed(&mut sequencer, "123456j"),
+            vec![Resolved::Sequence("down", Some(MAX_COUNT))]
+        );
+    }
+
+    #[test]
+    fn prefix_that_its_sequence_repeats_resolves_at_once() {
+        let mut sequencer = sequencer(&[("d", "delete"), ("dd", "delete"), ("x", "x")]);
+
+        assert_eq!(feed(&mut sequencer, "d"), vec![Resolved::Sequence("delete", None)]);
+        assert_eq!(feed(&mut sequencer, "d"), vec![]);
+        assert!(sequencer.pending().is_empty());
+        assert_eq!(
+            feed(&mut sequencer, "dx"),
+            vec![Resolved::Sequence("delete", None), Resolved::Sequence("x", None)]
+        );
+        assert_eq!(feed(&mut sequencer, "d"), vec![Resolved::Sequence("delete", None)]);
+        assert_eq!(timeout(&mut sequencer), vec![]);
+        assert_eq!(feed(&mut sequencer, "d"), vec![Resolved::Sequence("delete", None)]);
+    }
+
+    #[test]
+    fn default_keymap_deletes_with_a_count() {
+        let mut sequencer = KeySequencer::new();
+        for (keys, action) in Keymap::new().sequences() {
+            sequencer.register(&keys, action);
+        }
+
+        assert_eq!(feed(&mut sequencer, "d"), vec![Resolved::Sequence(Action::DeleteTask, None)]);
+        assert_eq!(feed(&mut sequencer, "d"), vec![]);
+        assert_eq!(
+            feed(&mut sequencer, "3dd"),
+            vec![Resolved::Sequence(Action::DeleteTask, Some(3))]
+        );
+        assert_eq!(feed(&mut sequencer, "2d"), vec![]);
+        assert_eq!(
+            timeout(&mut sequencer),
+            vec![Resolved::Sequence(Action::DeleteTask, Some(2))]
+        );
     }
 }
@@ -0,0 +1,603 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// The key tables a binding can live in. Prompts, the tag picker and the
+/// command line take text and are not configurable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum KeymapMode {
+    Visual,
+    Insert,
+    Calendar,
+}
+
+impl fmt::Display for KeymapMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeymapMode::Visual => write!(f, "visual"),
+            KeymapMode::Insert => write!(f, "insert"),
+            KeymapMode::Calendar => write!(f, "calendar"),
+        }
+    }
+}
+
+/// Something a key can be bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    ToggleHelp,
+    ToggleFocus,
+    NavigateDown,
+    NavigateUp,
+    NavigateTop,
+    NavigateBottom,
+    AddTaskBelow,
+    AddTaskAbove,
+    ToggleTask,
+    DeleteTask,
+    ChangeTask,
+    AppendToTask,
+    EditNotes,
+    VisualLine,
+    YankTask,
+    PasteBelow,
+    PasteAbove,
+    SetDueDate,
+    RaisePriority,
+    LowerPriority,
+    SortByPriority,
+    Indent,
+    Outdent,
+    MoveDown,
+    MoveUp,
+    MoveToTop,
+    MoveToBottom,
+    ToggleFold,
+    FoldAll,
+    UnfoldAll,
+    NextList,
+    PreviousList,
+    NewList,
+    RenameList,
+    DeleteList,
+    MoveToList,
+    TagPicker,
+    Search,
+    SearchNext,
+    SearchPrevious,
+    CommandLine,
+    Undo,
+    Redo,
+    ExitInsert,
+    NextTask,
+    CursorLeft,
+    CursorRight,
+    CursorHome,
+    CursorEnd,
+    WordLeft,
+    WordRight,
+    DeleteBackward,
+    DeleteForward,
+    DeleteWordBackward,
+    DeleteToStart,
+    FocusList,
+    DayBack,
+    DayForward,
+    WeekBack,
+    WeekForward,
+    MonthBack,
+    MonthForward,
+    Today,
+    FilterDay,
+    ClearDayFilter,
+}
+
+/// An entry of the action table: where the action is available, its name
+/// in the config file, the help line it is listed under and its default
+/// keys.
+struct ActionInfo {
+    mode: KeymapMode,
+    action: Action,
+    name: &'static str,
+    help: &'static str,
+    keys: &'static [&'static str],
+}
+
+const fn info(
+    mode: KeymapMode,
+    action: Action,
+    name: &'static str,
+    help: &'static str,
+    keys: &'static [&'static str],
+) -> ActionInfo {
+    ActionInfo {
+        mode,
+        action,
+        name,
+        help,
+        keys,
+    }
+}
+
+use Action::*;
+use KeymapMode::{Calendar, Insert, Visual};
+
+/// Every bindable action with its default keys, in help order. Actions that
+/// share a help line are listed on it together.
+const ACTIONS: &[ActionInfo] = &[
+    info(Visual, ToggleHelp, "toggle_help", "Toggle help", &["h"]),
+    info(Visual, Quit, "quit", "Quit", &["q", "<C-c>", "<Esc>"]),
+    info(Insert, ExitInsert, "exit_insert", "Exit insert", &["<C-c>", "<Esc>"]),
+    info(Insert, NextTask, "next_task", "Next task", &["<Enter>"]),
+    info(Insert, CursorLeft, "cursor_left", "Move cursor", &["<Left>"]),
+    info(Insert, CursorRight, "cursor_right", "Move cursor", &["<Right>"]),
+    info(Insert, CursorHome, "cursor_home", "Move cursor", &["<Home>"]),
+    info(Insert, CursorEnd, "cursor_end", "Move cursor", &["<End>"]),
+    info(Insert, WordLeft, "word_left", "Move by word", &["<A-b>"]),
+    info(Insert, WordRight, "word_right", "Move by word", &["<A-f>"]),
+    info(Insert, DeleteBackward, "delete_backward", "Delete", &["<BS>"]),
+    info(Insert, DeleteForward, "delete_forward", "Delete", &["<Del>"]),
+    info(Insert, DeleteWordBackward, "delete_word_backward", "Delete word", &["<C-w>"]),
+    info(Insert, DeleteToStart, "delete_to_start", "Clear to start", &["<C-u>"]),
+    info(Visual, NavigateDown, "navigate_down", "Navigate", &["j"]),
+    info(Visual, NavigateUp, "navigate_up", "Navigate", &["k"]),
+    info(Visual, NavigateTop, "navigate_top", "Navigate", &["gg"]),
+    info(Visual, NavigateBottom, "navigate_bottom", "Navigate", &["G"]),
+    info(Visual, AddTaskBelow, "add_task_below", "Add task", &["o"]),
+    info(Visual, AddTaskAbove, "add_task_above", "Add task", &["O"]),
+    info(Visual, ToggleTask, "toggle_task", "Toggle task", &["x"]),
+    // `dd` so that `3dd` deletes three tasks. A lone `d`, which is what a
+    // selection uses, deletes at once and only waits for more after a count.
+    info(Visual, DeleteTask, "delete_task", "Delete task", &["dd", "d"]),
+    info(Visual, ChangeTask, "change_task", "Change task", &["c"]),
+    info(Visual, AppendToTask, "append_to_task", "Append to task", &["a"]),
+    info(Visual, EditNotes, "edit_notes", "Edit notes", &["E"]),
+    info(Visual, VisualLine, "visual_line", "Select tasks", &["V"]),
+    info(Visual, YankTask, "yank_task", "Yank task", &["y"]),
+    info(Visual, PasteBelow, "paste_below", "Paste task", &["p"]),
+    info(Visual, PasteAbove, "paste_above", "Paste task", &["P"]),
+    info(Visual, SetDueDate, "set_due_date", "Set due date", &["D"]),
+    info(Visual, RaisePriority, "raise_priority", "Priority", &["+", "="]),
+    info(Visual, LowerPriority, "lower_priority", "Priority", &["-"]),
+    info(Visual, SortByPriority, "sort_by_priority", "Priority", &["S"]),
+    info(Visual, Indent, "indent", "Indent", &[">>"]),
+    info(Visual, Outdent, "outdent", "Indent", &["<<"]),
+    info(Visual, MoveDown, "move_down", "Move task", &["J"]),
+    info(Visual, MoveUp, "move_up", "Move task", &["K"]),
+    info(Visual, MoveToTop, "move_to_top", "Move task", &["gK"]),
+    info(Visual, MoveToBottom, "move_to_bottom", "Move task", &["gJ"]),
+    info(Visual, ToggleFold, "toggle_fold", "Fold", &["za"]),
+    info(Visual, FoldAll, "fold_all", "Fold", &["zM"]),
+    info(Visual, UnfoldAll, "unfold_all", "Fold", &["zR"]),
+    info(Visual, NextList, "next_list", "Switch list", &["gt"]),
+    info(Visual, PreviousList, "previous_list", "Switch list", &["gT"]),
+    info(Visual, NewList, "new_list", "New list", &["gn"]),
+    info(Visual, RenameList, "rename_list", "Rename list", &["R"]),
+    info(Visual, DeleteList, "delete_list", "Delete list", &["X"]),
+    info(Visual, MoveToList, "move_to_list", "Move to list", &["M"]),
+    info(Visual, TagPicker, "tag_picker", "Filter by tag", &["#"]),
+    info(Visual, Search, "search", "Search", &["/"]),
+    info(Visual, SearchNext, "search_next", "Search", &["n"]),
+    info(Visual, SearchPrevious, "search_previous", "Search", &["N"]),
+    info(Visual, CommandLine, "command_line", "Command line", &[":"]),
+    info(Visual, ToggleFocus, "toggle_focus", "Calendar", &["<Tab>"]),
+    info(Visual, Undo, "undo", "Undo / redo", &["u"]),
+    info(Visual, Redo, "redo", "Undo / redo", &["<C-r>"]),
+    info(Calendar, Quit, "quit", "Quit", &["q", "<C-c>"]),
+    info(Calendar, FocusList, "focus_list", "Back to list", &["<Tab>", "<Esc>"]),
+    info(Calendar, DayBack, "day_back", "Move day", &["h", "<Left>"]),
+    info(Calendar, DayForward, "day_forward", "Move day", &["l", "<Right>"]),
+    info(Calendar, WeekBack, "week_back", "Move week", &["k", "<Up>"]),
+    info(Calendar, WeekForward, "week_forward", "Move week", &["j", "<Down>"]),
+    info(Calendar, MonthBack, "month_back", "Move month", &["H", "<PageUp>"]),
+    info(Calendar, MonthForward, "month_forward", "Move month", &["L", "<PageDown>"]),
+    info(Calendar, Today, "today", "Today", &["t"]),
+    info(Calendar, FilterDay, "filter_day", "Filter by day", &["<Enter>"]),
+    info(Calendar, ClearDayFilter, "clear_day_filter", "Clear filter", &["x", "<BS>"]),
+];
+
+/// The key `<leader>` stands for unless the config sets another.
+pub const DEFAULT_LEADER: KeyEvent = KeyEvent::new(KeyCode::Char('\\'), KeyModifiers::NONE);
+
+/// A key or key sequence in the notation used by the config file: plain
+/// characters, or names in angle brackets such as `<Esc>`, `<C-r>` and
+/// `<leader>`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeySpec {
+    text: String,
+    keys: Vec<KeyEvent>,
+}
+
+impl KeySpec {
+    pub fn parse(text: &str, leader: KeyEvent) -> Result<KeySpec, String> {
+        let mut keys = Vec::new();
+        let mut rest = text;
+        while let Some(c) = rest.chars().next() {
+            // a `<` without a closing `>`, as in `<<`, is the key itself
+            let named = match c {
+                '<' => rest[1..]
+                    .find('>')
+                    .filter(|end| *end > 0 && !rest[1..end + 1].contains('<'))
+                    .map(|end| end + 1),
+                _ => None,
+            };
+            if let Some(end) = named {
+                let name = &rest[1..end];
+                keys.push(if name.eq_ignore_ascii_case("leader") {
+                    leader
+                } else {
+                    parse_named_key(name)?
+                });
+                rest = &rest[end + 1..];
+            } else {
+                keys.push(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+
+        if keys.is_empty() {
+            return Err("an empty key".to_string());
+        }
+        Ok(KeySpec {
+            text: text.to_string(),
+            keys,
+        })
+    }
+
+    pub fn keys(&self) -> &[KeyEvent] {
+        &self.keys
+    }
+}
+
+impl fmt::Display for KeySpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Writes a key the way the config file spells it.
+pub fn key_name(key_event: KeyEvent) -> String {
+    let name = match key_event.code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char('<') => "lt".to_string(),
+        KeyCode::Char(c) if key_event.modifiers.is_empty() => return c.to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::BackTab => "S-Tab".to_string(),
+        KeyCode::Backspace => "BS".to_string(),
+        KeyCode::Delete => "Del".to_string(),
+        KeyCode::Up => "Up".to_string(),
+        KeyCode::Down => "Down".to_string(),
+        KeyCode::Left => "Left".to_string(),
+        KeyCode::Right => "Right".to_string(),
+        KeyCode::Home => "Home".to_string(),
+        KeyCode::End => "End".to_string(),
+        KeyCode::PageUp => "PageUp".to_string(),
+        KeyCode::PageDown => "PageDown".to_string(),
+        KeyCode::Insert => "Insert".to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        _ => "?".to_string(),
+    };
+    let mut prefix = String::new();
+    if key_event.modifiers.contains(KeyModifiers::CONTROL) {
+        prefix.push_str("C-");
+    }
+    if key_event.modifiers.contains(KeyModifiers::ALT) {
+        prefix.push_str("A-");
+    }
+    if key_event.modifiers.contains(KeyModifiers::SHIFT) {
+        prefix.push_str("S-");
+    }
+    format!("<{}{}>", prefix, name)
+}
+
+/// Parses what is between `<` and `>`: optional `C-`, `A-` and `S-`
+/// modifiers followed by a key name or a single character.
+fn parse_named_key(name: &str) -> Result<KeyEvent, String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = name;
+    loop {
+        let modifier = match rest.get(..2) {
+            Some("C-" | "c-") => KeyModifiers::CONTROL,
+            Some("A-" | "a-" | "M-" | "m-") => KeyModifiers::ALT,
+            Some("S-" | "s-") => KeyModifiers::SHIFT,
+            _ => break,
+        };
+        // `<C-->` is control and the minus key
+        if rest.len() == 2 {
+            break;
+        }
+        modifiers |= modifier;
+        rest = &rest[2..];
+    }
+
+    let code = match rest.to_ascii_lowercase().as_str() {
+        "esc" => KeyCode::Esc,
+        "enter" | "cr" | "return" => KeyCode::Enter,
+        "tab" if modifiers.contains(KeyModifiers::SHIFT) => {
+            modifiers.remove(KeyModifiers::SHIFT);
+            KeyCode::BackTab
+        }
+        "tab" => KeyCode::Tab,
+        "bs" | "backspace" => KeyCode::Backspace,
+        "del" | "delete" => KeyCode::Delete,
+        "space" => KeyCode::Char(' '),
+        "lt" => KeyCode::Char('<'),
+        "gt" => KeyCode::Char('>'),
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "insert" => KeyCode::Insert,
+        lower => {
+            let mut chars = rest.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                    KeyCode::Char(c.to_ascii_uppercase())
+                }
+                (Some(c), None) => KeyCode::Char(c),
+                _ => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key name `<{}>`", name)),
+                },
+            }
+        }
+    };
+    Ok(normalize(KeyEvent::new(code, modifiers)))
+}
+
+/// Makes keys from the terminal comparable with parsed ones. Typed symbols
+/// may or may not carry SHIFT, and control letters may come in either case.
+pub fn normalize(key_event: KeyEvent) -> KeyEvent {
+    let mut modifiers = key_event.modifiers;
+    let mut code = key_event.code;
+    if let KeyCode::Char(c) = code {
+        modifiers.remove(KeyModifiers::SHIFT);
+        if modifiers.contains(KeyModifiers::CONTROL) {
+            code = KeyCode::Char(c.to_ascii_lowercase());
+        }
+    }
+    KeyEvent::new(code, modifiers)
+}
+
+/// Key overrides from the config file: for each mode, action names mapped
+/// to their new keys.
+pub type KeyOverrides = BTreeMap<KeymapMode, BTreeMap<String, Vec<String>>>;
+
+/// The key bindings in effect: the defaults from the action table with the
+/// config file's overrides applied.
+#[derive(Debug)]
+pub struct Keymap {
+    bindings: Vec<(KeymapMode, Action, Vec<KeySpec>)>,
+    single: HashMap<(KeymapMode, KeyEvent), Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::with_overrides(&KeyOverrides::new(), DEFAULT_LEADER).expect("default keys are valid")
+    }
+}
+
+impl Keymap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds the keymap. An action named in `overrides` gets exactly the
+    /// keys listed there, and those keys are taken away from the defaults
+    /// of other actions in the same mode.
+    pub fn with_overrides(overrides: &KeyOverrides, leader: KeyEvent) -> Result<Keymap, String> {
+        let mut bindings = Vec::new();
+        for (mode, actions) in overrides {
+            for (name, specs) in actions {
+                let info = ACTIONS
+                    .iter()
+                    .find(|info| info.mode == *mode && info.name == name)
+                    .ok_or_else(|| format!("keys.{}: unknown action `{}`", mode, name))?;
+                let specs = specs
+                    .iter()
+                    .map(|spec| KeySpec::parse(spec, leader))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| format!("keys.{}.{}: {}", mode, name, e))?;
+                bindings.push((*mode, info.action, specs));
+            }
+        }
+
+        let overridden = bindings
+            .iter()
+            .flat_map(|(mode, _, specs)| specs.iter().map(move |spec| (*mode, spec.keys().to_vec())))
+            .collect::<Vec<_>>();
+        for info in ACTIONS {
+            if bindings
+                .iter()
+                .any(|(mode, action, _)| *mode == info.mode && *action == info.action)
+            {
+                continue;
+            }
+            let specs = info
+                .keys
+                .iter()
+                .map(|spec| KeySpec::parse(spec, leader).expect("default keys are valid"))
+                .filter(|spec| !overridden.contains(&(info.mode, spec.keys().to_vec())))
+                .collect();
+            bindings.push((info.mode, info.action, specs));
+        }
+
+        let mut keymap = Keymap {
+            bindings,
+            single: HashMap::new(),
+        };
+        keymap.check_conflicts()?;
+        for (mode, action, specs) in &keymap.bindings {
+            for spec in specs {
+                if let [key] = spec.keys() {
+                    keymap.single.insert((*mode, *key), *action);
+                }
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// The action bound to a single key in `mode`.
+    pub fn lookup(&self, mode: KeymapMode, key_event: KeyEvent) -> Option<Action> {
+        self.single.get(&(mode, normalize(key_event))).copied()
+    }
+
+    /// Every binding of visual mode, single keys included, for the key
+    /// sequencer.
+    pub fn sequences(&self) -> Vec<(Vec<KeyEvent>, Action)> {
+        self.bindings
+            .iter()
+            .filter(|(mode, _, _)| *mode == KeymapMode::Visual)
+            .flat_map(|(_, action, specs)| {
+                specs.iter().map(move |spec| (spec.keys().to_vec(), *action))
+            })
+            .collect()
+    }
+
+    /// Help lines for `mode`: each help label of the action table with the
+    /// keys of all actions listed under it.
+    pub fn help(&self, mode: KeymapMode) -> Vec<(&'static str, String)> {
+        let mut lines: Vec<(&'static str, Vec<String>)> = Vec::new();
+        for info in ACTIONS.iter().filter(|info| info.mode == mode) {
+            let keys = self
+                .bindings
+                .iter()
+                .filter(|(m, action, _)| *m == mode && *action == info.action)
+                .flat_map(|(_, _, specs)| specs.iter().map(|spec| spec.to_string()));
+            match lines.iter_mut().find(|(help, _)| *help == info.help) {
+                Some((_, line_keys)) => line_keys.extend(keys),
+                None => lines.push((info.help, keys.collect())),
+            }
+        }
+        lines
+            .into_iter()
+            .filter(|(_, keys)| !keys.is_empty())
+            .map(|(help, keys)| (help, keys.join(", ")))
+            .collect()
+    }
+
+    /// Two actions on the same key would make one of them unreachable, and
+    /// so would a sequence outside visual mode, where keys are not
+    /// sequenced.
+    fn check_conflicts(&self) -> Result<(), String> {
+        let mut seen: HashMap<(KeymapMode, Vec<KeyEvent>), (Action, &KeySpec)> = HashMap::new();
+        for (mode, action, specs) in &self.bindings {
+            for spec in specs {
+                let key = (*mode, spec.keys().to_vec());
+                if let Some((other, _)) = seen.get(&key) {
+                    if other != action {
+                        return Err(format!(
+                            "keys.{}: `{}` is bound to both {} and {}",
+                            mode,
+                            spec,
+                            action_name(*mode, *other),
+                            action_name(*mode, *action)
+                        ));
+                    }
+                }
+                seen.insert(key, (*action, spec));
+            }
+        }
+
+        for ((mode, keys), (_, spec)) in &seen {
+            if *mode != KeymapMode::Visual && keys.len() > 1 {
+                return Err(format!(
+                    "keys.{}: `{}` is a sequence, only visual mode has those",
+                    mode, spec
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn action_name(mode: KeymapMode, action: Action) -> &'static str {
+    ACTIONS
+        .iter()
+        .find(|info| info.mode == mode && info.action == action)
+        .map(|info| info.name)
+        .unwrap_or("?")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    fn parse(text: &str) -> Result<Vec<KeyEvent>, String> {
+        KeySpec::parse(text, DEFAULT_LEADER).map(|spec| spec.keys().to_vec())
+    }
+
+    fn overrides(mode: KeymapMode, bindings: &[(&str, &str)]) -> KeyOverrides {
+        let keys = bindings
+            .iter()
+            .map(|(action, spec)| (action.to_string(), vec![spec.to_string()]))
+            .collect();
+        KeyOverrides::from([(mode, keys)])
+    }
+
+    #[test]
+    fn parses_characters_and_named_keys() {
+        let ctrl_r = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        assert_eq!(parse("gg"), Ok(vec![key('g'), key('g')]));
+        assert_eq!(parse("<C-r>"), Ok(vec![ctrl_r]));
+        assert_eq!(parse("<c-R>"), Ok(vec![ctrl_r]));
+        assert_eq!(parse("<Esc>"), Ok(vec![KeyEvent::from(KeyCode::Esc)]));
+        assert_eq!(parse("<S-Tab>"), Ok(vec![KeyEvent::from(KeyCode::BackTab)]));
+        assert_eq!(parse("<F5>"), Ok(vec![KeyEvent::from(KeyCode::F(5))]));
+        assert_eq!(parse("<lt>"), Ok(vec![key('<')]));
+        assert_eq!(parse("<<"), Ok(vec![key('<'), key('<')]));
+    }
+
+    #[test]
+    fn leader_stands_for_the_leader_key() {
+        assert_eq!(parse("<leader>a"), Ok(vec![DEFAULT_LEADER, key('a')]));
+        let spec = KeySpec::parse("<Leader>a", key(' ')).unwrap();
+        assert_eq!(spec.keys(), &[key(' '), key('a')]);
+    }
+
+    #[test]
+    fn rejects_unknown_key_names() {
+        assert_eq!(parse("<Foo>"), Err("unknown key name `<Foo>`".to_string()));
+        assert_eq!(parse("<F13>"), Err("unknown key name `<F13>`".to_string()));
+        assert_eq!(parse(""), Err("an empty key".to_string()));
+    }
+
+    #[test]
+    fn an_overridden_key_leaves_its_default_action() {
+        let keymap =
+            Keymap::with_overrides(&overrides(Visual, &[("quit", "j")]), DEFAULT_LEADER).unwrap();
+        assert_eq!(keymap.lookup(Visual, key('j')), Some(Quit));
+        assert_eq!(keymap.lookup(Visual, key('q')), None);
+        assert_eq!(keymap.lookup(Visual, key('k')), Some(NavigateUp));
+    }
+
+    #[test]
+    fn rejects_two_actions_on_one_key() {
+        let overrides = overrides(Visual, &[("quit", "j"), ("toggle_task", "j")]);
+        assert_eq!(
+            Keymap::with_overrides(&overrides, DEFAULT_LEADER).unwrap_err(),
+            "keys.visual: `j` is bound to both quit and toggle_task"
+        );
+    }
+
+    #[test]
+    fn rejects_sequences_outside_visual_mode() {
+        let overrides = overrides(Insert, &[("exit_insert", "jk")]);
+        assert_eq!(
+            Keymap::with_overrides(&overrides, DEFAULT_LEADER).unwrap_err(),
+            "keys.insert: `jk` is a sequence, only visual mode has those"
+        );
+    }
+}
@@ -4,6 +4,22 @@ pub mod ui;
 pub mod tui;
 pub mod handler;
 pub mod todo;
+pub mod task;
+pub mod task_list;
 pub mod key_sequencer;
 pub mod action_display;
 pub mod todo_serializer;
+pub mod history;
+pub mod due;
+pub mod view;
+pub mod tags;
+pub mod search;
+pub mod command;
+pub mod keymap;
+pub mod config;
+pub mod line_editor;
+pub mod hit_map;
+pub mod editor;
+pub mod file_stamp;
+pub mod merge;
+pub mod data_file;
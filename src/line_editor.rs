@@ -0,0 +1,246 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A single line of text with a cursor, for editing task titles.
+///
+/// The cursor is a byte offset that always sits on a grapheme boundary, so
+/// an emoji or an accented letter moves and deletes as one character.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LineEditor {
+    text: String,
+    cursor: usize,
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replaces the text and puts the cursor at its end.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+    }
+
+    /// The byte offset of the cursor in the text.
+    pub fn get_cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    pub fn insert_str(&mut self, s: &str) {
+        self.text.insert_str(self.cursor, s);
+        self.cursor += s.len();
+    }
+
+    /// Deletes the character before the cursor, like Backspace.
+    pub fn delete_backward(&mut self) {
+        let start = self.previous_boundary();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Deletes the character under the cursor, like Delete.
+    pub fn delete_forward(&mut self) {
+        let end = self.next_boundary();
+        self.text.replace_range(self.cursor..end, "");
+    }
+
+    /// Deletes back to the start of the word before the cursor, like
+    /// Ctrl-W. Words are separated by whitespace here.
+    pub fn delete_word_backward(&mut self) {
+        let start = self.word_start(char::is_whitespace);
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Deletes everything before the cursor, like Ctrl-U.
+    pub fn delete_to_start(&mut self) {
+        self.text.replace_range(..self.cursor, "");
+        self.cursor = 0;
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.previous_boundary();
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = self.next_boundary();
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.text.len();
+    }
+
+    /// Moves to the start of the word before the cursor, like Alt-b.
+    pub fn move_word_left(&mut self) {
+        self.cursor = self.word_start(is_separator);
+    }
+
+    /// Moves past the end of the word after the cursor, like Alt-f.
+    pub fn move_word_right(&mut self) {
+        let mut end = self.text.len();
+        let mut in_word = false;
+        for (offset, grapheme) in self.text[self.cursor..].grapheme_indices(true) {
+            let separator = grapheme.chars().all(is_separator);
+            if in_word && separator {
+                end = self.cursor + offset;
+                break;
+            }
+            in_word |= !separator;
+        }
+        self.cursor = end;
+    }
+
+    /// Where the word before the cursor starts: separators right before the
+    /// cursor are skipped, then everything up to the next separator.
+    fn word_start(&self, separator: fn(char) -> bool) -> usize {
+        let mut start = self.cursor;
+        let mut in_word = false;
+        for (offset, grapheme) in self.text[..self.cursor].grapheme_indices(true).rev() {
+            let separates = grapheme.chars().all(separator);
+            if in_word && separates {
+                break;
+            }
+            in_word |= !separates;
+            start = offset;
+        }
+        start
+    }
+
+    fn previous_boundary(&self) -> usize {
+        self.text[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(offset, _)| offset)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.text[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.cursor, |grapheme| self.cursor + grapheme.len())
+    }
+}
+
+/// What separates words for the word motions.
+fn is_separator(c: char) -> bool {
+    !c.is_alphanumeric() && c != '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(text: &str) -> LineEditor {
+        let mut editor = LineEditor::new();
+        editor.set_text(text);
+        editor
+    }
+
+    #[test]
+    fn backspace_deletes_whole_multibyte_characters() {
+        let mut editor = editor("café");
+        editor.delete_backward();
+        assert_eq!(editor.get_text(), "caf");
+        assert_eq!(editor.get_cursor(), 3);
+    }
+
+    #[test]
+    fn backspace_deletes_whole_emoji() {
+        // a family emoji is several code points joined into one grapheme
+        let mut editor = editor("hi 👨‍👩‍👧🎉");
+        editor.delete_backward();
+        assert_eq!(editor.get_text(), "hi 👨‍👩‍👧");
+        editor.delete_backward();
+        assert_eq!(editor.get_text(), "hi ");
+    }
+
+    #[test]
+    fn backspace_at_start_does_nothing() {
+        let mut editor = editor("a");
+        editor.move_home();
+        editor.delete_backward();
+        assert_eq!(editor.get_text(), "a");
+        assert_eq!(editor.get_cursor(), 0);
+    }
+
+    #[test]
+    fn cursor_moves_over_graphemes() {
+        let mut editor = editor("é🎉x");
+        editor.move_left();
+        editor.move_left();
+        assert_eq!(editor.get_cursor(), "é".len());
+        editor.delete_forward();
+        assert_eq!(editor.get_text(), "éx");
+        editor.move_right();
+        editor.move_right();
+        assert_eq!(editor.get_cursor(), "éx".len());
+    }
+
+    #[test]
+    fn inserts_in_the_middle() {
+        let mut editor = editor("buy milk");
+        editor.move_home();
+        editor.move_word_right();
+        editor.insert_str(" oat");
+        assert_eq!(editor.get_text(), "buy oat milk");
+        editor.insert('!');
+        assert_eq!(editor.get_text(), "buy oat! milk");
+        assert_eq!(editor.get_cursor(), "buy oat!".len());
+    }
+
+    #[test]
+    fn word_motions_stop_at_separators() {
+        let mut editor = editor("call mom, re: #home");
+        editor.move_word_left();
+        assert_eq!(editor.get_cursor(), "call mom, re: #".len());
+        editor.move_word_left();
+        assert_eq!(editor.get_cursor(), "call mom, ".len());
+        editor.move_word_left();
+        assert_eq!(editor.get_cursor(), "call ".len());
+        editor.move_word_right();
+        assert_eq!(editor.get_cursor(), "call mom".len());
+        editor.move_word_right();
+        assert_eq!(editor.get_cursor(), "call mom, re".len());
+    }
+
+    #[test]
+    fn word_motions_treat_accented_letters_as_word() {
+        let mut editor = editor("crème brûlée");
+        editor.move_word_left();
+        assert_eq!(editor.get_cursor(), "crème ".len());
+        editor.move_home();
+        editor.move_word_right();
+        assert_eq!(editor.get_cursor(), "crème".len());
+    }
+
+    #[test]
+    fn delete_word_backward_splits_on_whitespace() {
+        let mut editor = editor("email bob@example.com  ");
+        editor.delete_word_backward();
+        assert_eq!(editor.get_text(), "email ");
+        editor.delete_word_backward();
+        assert_eq!(editor.get_text(), "");
+    }
+
+    #[test]
+    fn delete_to_start_keeps_text_after_cursor() {
+        let mut editor = editor("draft reply");
+        editor.move_word_left();
+        editor.delete_to_start();
+        assert_eq!(editor.get_text(), "reply");
+        assert_eq!(editor.get_cursor(), 0);
+    }
+}
@@ -2,14 +2,71 @@
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
 use std::io;
-use todo_term::app::{App, AppMode, AppResult};
+use std::path::PathBuf;
+use todo_term::app::{App, AppMode, AppResult, Focus};
+use todo_term::config::Config;
+use todo_term::data_file;
+use todo_term::editor;
 use todo_term::event::{Event, EventHandler};
-use todo_term::handler::{handle_key_events, handle_normal_mode_sequence_key_events, setup_key_sequences};
+use todo_term::handler::{
+    handle_key_events, handle_key_sequence_events, handle_key_sequence_timeout, handle_mouse_events,
+    handle_paste_events, setup_key_sequences,
+};
 use todo_term::key_sequencer::KeySequencer;
 use todo_term::tui::Tui;
 
+const USAGE: &str = "\
+Usage: todo-term [--file <path>]
+
+Options:
+  -f, --file <path>  The todo file to open. Without it, $TODO_TERM_FILE, a
+                     todo.json in the current directory or above it, then
+                     todo.json in the todo-term data directory is used.
+  -h, --help         Show this help.";
+
+/// The `--file` flag, if given. Exits for `--help` and anything unknown.
+fn parse_args() -> Option<String> {
+    let mut file = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            "-f" | "--file" => match args.next() {
+                Some(path) => file = Some(path),
+                None => usage_error(&format!("{} needs a path", arg)),
+            },
+            _ => match arg.strip_prefix("--file=") {
+                Some(path) => file = Some(path.to_string()),
+                None => usage_error(&format!("unknown argument {}", arg)),
+            },
+        }
+    }
+    file
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("todo-term: {}\n\n{}", message, USAGE);
+    std::process::exit(2);
+}
+
 fn main() -> AppResult<()> {
-    let mut app = App::new();
+    let file = parse_args();
+
+    // a broken config is reported before the terminal is taken over
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("todo-term: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let file_path = data_file::resolve(file.as_deref());
+    let mut app = App::with_file(&file_path.to_string_lossy());
+    app.set_keymap(config.keymap);
 
     let backend = CrosstermBackend::new(io::stderr());
     let terminal = Terminal::new(backend)?;
@@ -20,24 +77,41 @@ fn main() -> AppResult<()> {
 
     let mut key_sequencer = KeySequencer::default();
 
-    setup_key_sequences(&mut key_sequencer);
+    setup_key_sequences(&mut key_sequencer, app.get_keymap());
 
     while app.is_running() {
+        if let Some(text) = app.take_editor_request() {
+            tui.suspend()?;
+            let result = editor::edit(&text);
+            tui.resume()?;
+            app.finish_editor(result);
+            app.save();
+        }
+
+        tui.events.watch(PathBuf::from(app.get_file_path()));
         tui.draw(&mut app)?;
         match tui.events.next()? {
             Event::Tick => {
                 app.tick();
-                key_sequencer.tick();
+                handle_key_sequence_timeout(&mut key_sequencer, &mut app)?;
             }
-            Event::Key(key_event) => match (app.get_mode(), key_sequencer.feed(key_event)) {
-                (AppMode::Visual, Some(key_sequence_event)) => {
-                    handle_normal_mode_sequence_key_events(key_sequence_event, &mut app)?;
+            Event::Key(key_event) => match (app.get_mode(), app.get_focus()) {
+                (AppMode::Visual, Focus::List) | (AppMode::VisualLine, _) => {
+                    handle_key_sequence_events(key_event, &mut key_sequencer, &mut app)?;
                 }
                 _ => {
                     handle_key_events(key_event, &mut app)?;
                 }
             },
-            Event::Mouse(_) => {}
+            Event::Paste(text) => {
+                handle_paste_events(text, &mut app)?;
+            }
+            Event::Mouse(mouse_event) => {
+                handle_mouse_events(mouse_event, &mut app)?;
+            }
+            Event::FileChanged => {
+                app.check_file();
+            }
             Event::Resize(_, _) => {}
         }
     }
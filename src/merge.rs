@@ -0,0 +1,200 @@
+use crate::task::Task;
+
+/// Merges two edited copies of a sequence with the copy they both started
+/// from. Items are matched by `key`:
+///
+/// - items on both sides are combined with `merge`, given the base item if
+///   there was one,
+/// - items added on one side are kept, items from the other side land
+///   after the item they followed there,
+/// - items deleted on one side are dropped, unless the other side changed
+///   them.
+///
+/// The order of `local` wins where both sides reordered the items.
+pub fn merge_by_key<T: Clone + PartialEq>(
+    base: &[T],
+    local: &[T],
+    remote: &[T],
+    key: impl Fn(&T) -> &str,
+    merge: impl Fn(Option<&T>, &T, &T) -> T,
+) -> Vec<T> {
+    let same_order = |a: &[T], b: &[T]| {
+        a.len() == b.len() && a.iter().zip(b).all(|(a, b)| key(a) == key(b))
+    };
+
+    // only the other side reordered, so its order is the one to keep
+    if same_order(local, base) && !same_order(remote, base) {
+        merge_in_order(base, remote, local, &key, &|base, remote, local| {
+            merge(base, local, remote)
+        })
+    } else {
+        merge_in_order(base, local, remote, &key, &merge)
+    }
+}
+
+/// [`merge_by_key`] in the order of `first`.
+fn merge_in_order<T: Clone + PartialEq>(
+    base: &[T],
+    first: &[T],
+    second: &[T],
+    key: &dyn Fn(&T) -> &str,
+    merge: &dyn Fn(Option<&T>, &T, &T) -> T,
+) -> Vec<T> {
+    let find = |items: &'_ [T], item: &T| items.iter().position(|other| key(other) == key(item));
+
+    let mut merged = Vec::new();
+    for item in first {
+        let base_item = find(base, item).map(|i| &base[i]);
+        match (base_item, find(second, item)) {
+            (base_item, Some(i)) => merged.push(merge(base_item, item, &second[i])),
+            (None, None) => merged.push(item.clone()),
+            // deleted on the other side, but changed on this one
+            (Some(base_item), None) if base_item != item => merged.push(item.clone()),
+            (Some(_), None) => {}
+        }
+    }
+
+    for (index, item) in second.iter().enumerate() {
+        if find(first, item).is_some() {
+            continue;
+        }
+        // deleted on the other side and left alone on this one
+        if find(base, item).is_some_and(|i| base[i] == *item) {
+            continue;
+        }
+        let position = second[..index]
+            .iter()
+            .rev()
+            .find_map(|previous| find(&merged, previous))
+            .map_or(0, |i| i + 1);
+        merged.insert(position, item.clone());
+    }
+
+    merged
+}
+
+/// Merges a task changed on both sides. A task's own fields are taken as a
+/// whole from the side that changed them, or from here if both did. Its
+/// subtasks are merged one by one.
+pub fn merge_task(base: Option<&Task>, local: &Task, remote: &Task) -> Task {
+    let mut merged = match base {
+        Some(base) if same_fields(base, local) => remote.clone(),
+        _ => local.clone(),
+    };
+    let base_subtasks = base.map_or(&[][..], |base| base.subtasks());
+    *merged.subtasks_mut() =
+        merge_tasks(base_subtasks, local.subtasks(), remote.subtasks());
+    merged
+}
+
+pub fn merge_tasks(base: &[Task], local: &[Task], remote: &[Task]) -> Vec<Task> {
+    merge_by_key(base, local, remote, Task::id, merge_task)
+}
+
+/// Whether two tasks are the same apart from their subtasks.
+fn same_fields(a: &Task, b: &Task) -> bool {
+    let without_subtasks = |task: &Task| {
+        let mut task = task.clone();
+        task.subtasks_mut().clear();
+        task
+    };
+    without_subtasks(a) == without_subtasks(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Item(&'static str, u32);
+
+    /// Takes the remote item when only the remote side changed it.
+    fn merge(base: &[Item], local: &[Item], remote: &[Item]) -> Vec<Item> {
+        merge_by_key(base, local, remote, |item| item.0, |base, local, remote| {
+            match base {
+                Some(base) if base == local => remote.clone(),
+                _ => local.clone(),
+            }
+        })
+    }
+
+    #[test]
+    fn keeps_items_added_on_both_sides() {
+        let base = [Item("a", 0), Item("z", 0)];
+        let local = [Item("a", 0), Item("b", 0), Item("z", 0)];
+        let remote = [Item("a", 0), Item("z", 0), Item("c", 0)];
+
+        assert_eq!(
+            merge(&base, &local, &remote),
+            vec![Item("a", 0), Item("b", 0), Item("z", 0), Item("c", 0)]
+        );
+    }
+
+    #[test]
+    fn merges_an_item_added_on_both_sides_once() {
+        assert_eq!(merge(&[], &[Item("a", 1)], &[Item("a", 2)]), vec![Item("a", 1)]);
+    }
+
+    #[test]
+    fn takes_changes_from_either_side() {
+        let base = [Item("a", 0), Item("b", 0)];
+        let local = [Item("a", 1), Item("b", 0)];
+        let remote = [Item("a", 0), Item("b", 2)];
+
+        assert_eq!(merge(&base, &local, &remote), vec![Item("a", 1), Item("b", 2)]);
+    }
+
+    #[test]
+    fn keeps_an_item_deleted_on_one_side_but_edited_on_the_other() {
+        let base = [Item("a", 0), Item("b", 0)];
+        let edited = [Item("a", 0), Item("b", 1)];
+        let deleted = [Item("a", 0)];
+
+        assert_eq!(merge(&base, &deleted, &edited), vec![Item("a", 0), Item("b", 1)]);
+        assert_eq!(merge(&base, &edited, &deleted), vec![Item("a", 0), Item("b", 1)]);
+    }
+
+    #[test]
+    fn drops_an_item_deleted_on_one_side_and_left_alone_on_the_other() {
+        let base = [Item("a", 0), Item("b", 0)];
+        let deleted = [Item("a", 0)];
+
+        assert_eq!(merge(&base, &deleted, &base), vec![Item("a", 0)]);
+        assert_eq!(merge(&base, &base, &deleted), vec![Item("a", 0)]);
+    }
+
+    #[test]
+    fn keeps_the_order_of_the_side_that_reordered() {
+        let base = [Item("a", 0), Item("b", 0), Item("c", 0)];
+        let reordered = [Item("c", 0), Item("a", 0), Item("b", 0)];
+
+        assert_eq!(merge(&base, &base, &reordered), reordered.to_vec());
+        assert_eq!(merge(&base, &reordered, &base), reordered.to_vec());
+    }
+
+    #[test]
+    fn keeps_the_local_order_when_both_sides_reordered() {
+        let base = [Item("a", 0), Item("b", 0), Item("c", 0)];
+        let local = [Item("b", 0), Item("a", 0), Item("c", 0)];
+        let remote = [Item("c", 0), Item("b", 0), Item("a", 0)];
+
+        assert_eq!(merge(&base, &local, &remote), local.to_vec());
+    }
+
+    #[test]
+    fn merges_subtasks_of_a_task_changed_on_both_sides() {
+        let mut base = Task::new("Plan trip".to_string());
+        base.subtasks_mut().push(Task::new("Book hotel".to_string()));
+
+        let mut local = base.clone();
+        local.set_title("Plan the trip".to_string());
+        let mut remote = base.clone();
+        remote.subtasks_mut().push(Task::new("Pack".to_string()));
+
+        let merged = merge_task(Some(&base), &local, &remote);
+        assert_eq!(merged.title(), "Plan the trip");
+        let subtasks = merged.subtasks().iter().map(Task::id).collect::<Vec<_>>();
+        let expected = remote.subtasks().iter().map(Task::id).collect::<Vec<_>>();
+        assert_eq!(subtasks, expected);
+    }
+}
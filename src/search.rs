@@ -0,0 +1,90 @@
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+
+/// The pattern typed after `/`.
+///
+/// Plain patterns match literally, regex patterns are compiled as they are.
+/// Either way the search ignores case unless the pattern has an uppercase
+/// letter.
+#[derive(Clone, Debug, Default)]
+pub struct Search {
+    pattern: String,
+    regex: bool,
+    compiled: Option<Regex>,
+    error: Option<String>,
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn set_pattern(&mut self, pattern: &str) {
+        self.pattern = pattern.to_string();
+        self.compile();
+    }
+
+    pub fn clear(&mut self) {
+        self.set_pattern("");
+    }
+
+    pub fn is_regex(&self) -> bool {
+        self.regex
+    }
+
+    pub fn toggle_regex(&mut self) {
+        self.regex = !self.regex;
+        self.compile();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pattern.is_empty()
+    }
+
+    /// Why the pattern is not a valid regex, if it is not.
+    pub fn get_error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.compiled
+            .as_ref()
+            .is_some_and(|regex| regex.is_match(text))
+    }
+
+    /// Byte ranges of the non-empty matches in `text`.
+    pub fn find(&self, text: &str) -> Vec<Range<usize>> {
+        match &self.compiled {
+            Some(regex) => regex
+                .find_iter(text)
+                .filter(|m| !m.is_empty())
+                .map(|m| m.range())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn compile(&mut self) {
+        self.compiled = None;
+        self.error = None;
+        if self.pattern.is_empty() {
+            return;
+        }
+
+        let source = if self.regex {
+            self.pattern.clone()
+        } else {
+            regex::escape(&self.pattern)
+        };
+        let ignore_case = !self.pattern.chars().any(char::is_uppercase);
+        match RegexBuilder::new(&source).case_insensitive(ignore_case).build() {
+            Ok(regex) => self.compiled = Some(regex),
+            Err(_) => self.error = Some(format!("Invalid regex: {}", self.pattern)),
+        }
+    }
+}
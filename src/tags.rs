@@ -0,0 +1,52 @@
+use std::ops::Range;
+
+/// Characters that start a tag: `#tag` for topics and `@context` for where
+/// or with whom a task gets done.
+pub const TAG_SIGILS: [char; 2] = ['#', '@'];
+
+/// Byte ranges of the tag tokens in `text`, sigil included.
+///
+/// A tag starts at the beginning of the text or after whitespace and runs
+/// over letters, digits, `_`, `-` and `/`. A lone sigil is not a tag.
+pub fn tag_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    let mut at_word_start = true;
+
+    while let Some((start, c)) = chars.next() {
+        if at_word_start && TAG_SIGILS.contains(&c) {
+            let mut end = start + c.len_utf8();
+            while let Some(&(i, next)) = chars.peek() {
+                if !is_tag_char(next) {
+                    break;
+                }
+                end = i + next.len_utf8();
+                chars.next();
+            }
+            if end > start + c.len_utf8() {
+                ranges.push(start..end);
+            }
+            at_word_start = false;
+            continue;
+        }
+        at_word_start = c.is_whitespace();
+    }
+
+    ranges
+}
+
+/// The distinct tags in `text`, lowercased, in order of appearance.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for range in tag_ranges(text) {
+        let tag = text[range].to_lowercase();
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '/'
+}
@@ -0,0 +1,263 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use time::{Date, OffsetDateTime};
+
+use crate::tags;
+
+time::serde::format_description!(due_date_format, Date, "[year]-[month]-[day]");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Task {
+    id: String,
+    title: String,
+    #[serde(with = "time::serde::rfc3339")]
+    created_at: OffsetDateTime,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    completed_at: Option<OffsetDateTime>,
+    #[serde(
+        default,
+        with = "due_date_format::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    due: Option<Date>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    priority: Option<Priority>,
+    /// Multi-line notes, written in `$EDITOR`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    notes: String,
+    /// `#tag` and `@context` tokens from the title, lowercased. Updated by
+    /// [`update_tags`](Task::update_tags) when editing ends.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    subtasks: Vec<Task>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    folded: bool,
+    /// Free-form metadata. Keys this build doesn't know end up here too and
+    /// are written back as they were, so newer fields survive older builds.
+    #[serde(flatten)]
+    fields: BTreeMap<String, serde_json::Value>,
+}
+
+impl Task {
+    pub fn new(title: String) -> Task {
+        Task {
+            id: generate_id(),
+            created_at: OffsetDateTime::now_utc(),
+            completed_at: None,
+            due: None,
+            priority: None,
+            notes: String::new(),
+            tags: tags::parse_tags(&title),
+            subtasks: Vec::new(),
+            folded: false,
+            fields: BTreeMap::new(),
+            title,
+        }
+    }
+
+    /// A task with a known id, for formats that derive ids themselves.
+    pub fn with_id(id: String, title: String) -> Task {
+        Task {
+            id,
+            ..Task::new(title)
+        }
+    }
+
+    /// Returns a copy of this task and its subtasks with fresh ids, used
+    /// when pasting.
+    pub fn duplicate(&self) -> Task {
+        Task {
+            id: generate_id(),
+            subtasks: self.subtasks.iter().map(Task::duplicate).collect(),
+            ..self.clone()
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn set_title(&mut self, title: String) {
+        self.title = title;
+    }
+
+    pub fn notes(&self) -> &str {
+        &self.notes
+    }
+
+    pub fn set_notes(&mut self, notes: String) {
+        self.notes = notes;
+    }
+
+    pub fn created_at(&self) -> OffsetDateTime {
+        self.created_at
+    }
+
+    pub fn completed_at(&self) -> Option<OffsetDateTime> {
+        self.completed_at
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.completed_at.is_some()
+    }
+
+    pub fn complete(&mut self) {
+        self.completed_at = Some(OffsetDateTime::now_utc());
+    }
+
+    pub fn uncomplete(&mut self) {
+        self.completed_at = None;
+    }
+
+    pub fn due(&self) -> Option<Date> {
+        self.due
+    }
+
+    pub fn set_due(&mut self, due: Option<Date>) {
+        self.due = due;
+    }
+
+    pub fn priority(&self) -> Option<Priority> {
+        self.priority
+    }
+
+    pub fn set_priority(&mut self, priority: Option<Priority>) {
+        self.priority = priority;
+    }
+
+    /// Steps the priority up: none, low, medium, high.
+    pub fn raise_priority(&mut self) {
+        self.priority = match self.priority {
+            None => Some(Priority::Low),
+            Some(Priority::Low) => Some(Priority::Medium),
+            Some(Priority::Medium) | Some(Priority::High) => Some(Priority::High),
+        };
+    }
+
+    /// Steps the priority down: high, medium, low, none.
+    pub fn lower_priority(&mut self) {
+        self.priority = match self.priority {
+            Some(Priority::High) => Some(Priority::Medium),
+            Some(Priority::Medium) => Some(Priority::Low),
+            Some(Priority::Low) | None => None,
+        };
+    }
+
+    pub fn tags(&self) -> &Vec<String> {
+        &self.tags
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Re-reads the tags from the title.
+    pub fn update_tags(&mut self) {
+        self.tags = tags::parse_tags(&self.title);
+    }
+
+    /// Re-reads the tags of this task and all of its descendants.
+    pub fn update_all_tags(&mut self) {
+        self.update_tags();
+        for task in &mut self.subtasks {
+            task.update_all_tags();
+        }
+    }
+
+    pub fn subtasks(&self) -> &Vec<Task> {
+        &self.subtasks
+    }
+
+    pub fn subtasks_mut(&mut self) -> &mut Vec<Task> {
+        &mut self.subtasks
+    }
+
+    pub fn is_folded(&self) -> bool {
+        self.folded
+    }
+
+    pub fn set_folded(&mut self, folded: bool) {
+        self.folded = folded;
+    }
+
+    /// Completed and total counts over all descendants.
+    pub fn progress(&self) -> (usize, usize) {
+        self.subtasks.iter().fold((0, 0), |(done, total), task| {
+            let (sub_done, sub_total) = task.progress();
+            (
+                done + sub_done + task.is_complete() as usize,
+                total + sub_total + 1,
+            )
+        })
+    }
+
+    pub fn has_incomplete_subtasks(&self) -> bool {
+        let (done, total) = self.progress();
+        done < total
+    }
+
+    /// Completes every descendant that is not complete yet.
+    pub fn complete_subtasks(&mut self) {
+        for task in &mut self.subtasks {
+            if !task.is_complete() {
+                task.complete();
+            }
+            task.complete_subtasks();
+        }
+    }
+
+    /// Visits this task and its descendants depth-first.
+    pub fn walk<'a>(&'a self, visit: &mut impl FnMut(&'a Task)) {
+        visit(self);
+        for task in &self.subtasks {
+            task.walk(visit);
+        }
+    }
+
+    pub fn get_field(&self, key: &str) -> Option<&serde_json::Value> {
+        self.fields.get(key)
+    }
+
+    pub fn set_field(&mut self, key: &str, value: serde_json::Value) {
+        self.fields.insert(key.to_string(), value);
+    }
+
+    pub fn remove_field(&mut self, key: &str) -> Option<serde_json::Value> {
+        self.fields.remove(key)
+    }
+}
+
+fn generate_id() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_fields_it_doesnt_know() {
+        let json = serde_json::json!({
+            "id": "1",
+            "title": "a",
+            "created_at": "2026-10-18T00:00:00Z",
+            "color": "red",
+        });
+        let task: Task = serde_json::from_value(json).unwrap();
+        assert_eq!(serde_json::to_value(&task).unwrap()["color"], "red");
+    }
+}
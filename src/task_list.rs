@@ -0,0 +1,523 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use time::Date;
+
+use crate::merge;
+use crate::task::Task;
+
+/// A named list of tasks. A [`Todo`](crate::todo::Todo) holds several.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TaskList {
+    name: String,
+    complete_tasks: Vec<Task>,
+    incomplete_tasks: Vec<Task>,
+    /// Shows incomplete tasks ordered by priority, then by manual order.
+    /// The tasks stay stored in manual order.
+    #[serde(default)]
+    sort_by_priority: bool,
+    /// Free-form metadata, with unknown keys kept as-is like [`Task`]'s.
+    #[serde(flatten)]
+    fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// Addresses a task in the tree: the index among top-level tasks
+/// (incomplete tasks first, then complete ones), followed by the index
+/// among its parent's subtasks at each level below.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TaskPath(Vec<usize>);
+
+impl TaskPath {
+    pub fn root(index: usize) -> TaskPath {
+        TaskPath(vec![index])
+    }
+
+    pub fn child(&self, index: usize) -> TaskPath {
+        let mut path = self.0.clone();
+        path.push(index);
+        TaskPath(path)
+    }
+
+    /// The sibling of this task at `index`.
+    pub fn sibling(&self, index: usize) -> TaskPath {
+        let mut path = self.0.clone();
+        *path.last_mut().unwrap() = index;
+        TaskPath(path)
+    }
+
+    pub fn parent(&self) -> Option<TaskPath> {
+        match self.0.len() {
+            1 => None,
+            n => Some(TaskPath(self.0[..n - 1].to_vec())),
+        }
+    }
+
+    /// 0 for top-level tasks.
+    pub fn depth(&self) -> usize {
+        self.0.len() - 1
+    }
+
+    /// The index among its siblings.
+    pub fn index(&self) -> usize {
+        *self.0.last().unwrap()
+    }
+
+    /// The index of its top-level ancestor, or itself.
+    pub fn top(&self) -> usize {
+        self.0[0]
+    }
+}
+
+/// Where [`TaskList::move_task`] moves a task among its siblings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TaskMove {
+    Up,
+    Down,
+    Top,
+    Bottom,
+}
+
+impl TaskList {
+    pub fn new(name: String) -> TaskList {
+        TaskList {
+            name,
+            ..TaskList::default()
+        }
+    }
+
+    /// A list holding `tasks`, complete and incomplete ones each in the
+    /// order given.
+    pub fn with_tasks(name: String, tasks: Vec<Task>) -> TaskList {
+        let (complete_tasks, incomplete_tasks) = tasks.into_iter().partition(Task::is_complete);
+        TaskList {
+            name,
+            complete_tasks,
+            incomplete_tasks,
+            ..TaskList::default()
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    /// Inserts `task` so that it ends up at `path`. Top-level tasks can only
+    /// be inserted among the incomplete tasks and are marked incomplete.
+    pub fn add_task(&mut self, path: &TaskPath, mut task: Task) {
+        match path.parent() {
+            None => {
+                assert!(path.index() <= self.incomplete_tasks.len());
+
+                task.uncomplete();
+                self.incomplete_tasks.insert(path.index(), task);
+            }
+            Some(parent) => {
+                let subtasks = self.get_task_mut(&parent).subtasks_mut();
+                assert!(path.index() <= subtasks.len());
+
+                subtasks.insert(path.index(), task);
+            }
+        }
+    }
+
+    /// Flips the task's completion. Completing a task can also complete all
+    /// of its subtasks. Top-level tasks move between the incomplete and
+    /// complete lists; subtasks stay in place.
+    pub fn toggle_task(&mut self, path: &TaskPath, with_subtasks: bool) {
+        let task = self.get_task_mut(path);
+        let completing = !task.is_complete();
+        if completing && with_subtasks {
+            task.complete_subtasks();
+        }
+
+        if path.depth() > 0 {
+            if completing {
+                task.complete();
+            } else {
+                task.uncomplete();
+            }
+            return;
+        }
+
+        let index = path.index();
+        if index < self.incomplete_tasks.len() {
+            let mut task = self.incomplete_tasks.remove(index);
+            task.complete();
+            self.complete_tasks.insert(0, task);
+        } else {
+            let mut task = self
+                .complete_tasks
+                .remove(index - self.incomplete_tasks.len());
+            task.uncomplete();
+            self.incomplete_tasks.push(task);
+        }
+    }
+
+    /// Removes the task and its subtasks.
+    pub fn delete_task(&mut self, path: &TaskPath) -> Task {
+        match path.parent() {
+            None => {
+                let index = path.index();
+                assert!(index < self.len());
+
+                if index < self.incomplete_tasks.len() {
+                    self.incomplete_tasks.remove(index)
+                } else {
+                    self.complete_tasks
+                        .remove(index - self.incomplete_tasks.len())
+                }
+            }
+            Some(parent) => self.get_task_mut(&parent).subtasks_mut().remove(path.index()),
+        }
+    }
+
+    /// Makes the task the last subtask of the sibling above it. Returns
+    /// false if there is no such sibling.
+    pub fn indent_task(&mut self, path: &TaskPath) -> bool {
+        if path.index() == 0 {
+            return false;
+        }
+
+        let new_parent = path.sibling(path.index() - 1);
+        let task = self.delete_task(path);
+        let new_parent = self.get_task_mut(&new_parent);
+        new_parent.set_folded(false);
+        new_parent.subtasks_mut().push(task);
+        true
+    }
+
+    /// Moves the task out of its parent, right below it. Returns false for
+    /// top-level tasks.
+    pub fn outdent_task(&mut self, path: &TaskPath) -> bool {
+        let Some(parent) = path.parent() else {
+            return false;
+        };
+
+        let task = self.delete_task(path);
+        match parent.parent() {
+            Some(grandparent) => self
+                .get_task_mut(&grandparent)
+                .subtasks_mut()
+                .insert(parent.index() + 1, task),
+            None => self.insert_top_level_after(parent.index(), task),
+        }
+        true
+    }
+
+    /// Moves the task among its siblings. Top-level tasks stay on their
+    /// side of the boundary between incomplete and complete tasks, and
+    /// while sorting by priority an incomplete task stays among the tasks
+    /// of its priority. Returns false if the task is already as far as it
+    /// can go.
+    pub fn move_task(&mut self, path: &TaskPath, to: TaskMove) -> bool {
+        let (siblings, index, range) = match path.parent() {
+            Some(parent) => {
+                let subtasks = self.get_task_mut(&parent).subtasks_mut();
+                let len = subtasks.len();
+                (subtasks, path.index(), 0..len)
+            }
+            None if path.index() < self.incomplete_tasks.len() && self.sort_by_priority => {
+                return self.move_within_priority(path.index(), to);
+            }
+            None if path.index() < self.incomplete_tasks.len() => {
+                let len = self.incomplete_tasks.len();
+                (&mut self.incomplete_tasks, path.index(), 0..len)
+            }
+            None => {
+                let index = path.index() - self.incomplete_tasks.len();
+                let len = self.complete_tasks.len();
+                (&mut self.complete_tasks, index, 0..len)
+            }
+        };
+
+        let target = match to {
+            TaskMove::Up if index > range.start => index - 1,
+            TaskMove::Down if index + 1 < range.end => index + 1,
+            TaskMove::Top => range.start,
+            TaskMove::Bottom => range.end - 1,
+            _ => index,
+        };
+        if target == index {
+            return false;
+        }
+        let task = siblings.remove(index);
+        siblings.insert(target, task);
+        true
+    }
+
+    /// Moves an incomplete task past the tasks of its priority, the ones it
+    /// is shown among while sorting by priority.
+    fn move_within_priority(&mut self, index: usize, to: TaskMove) -> bool {
+        let priority = self.incomplete_tasks[index].priority();
+        let peers = (0..self.incomplete_tasks.len())
+            .filter(|&i| self.incomplete_tasks[i].priority() == priority)
+            .collect::<Vec<_>>();
+        let at = peers.iter().position(|&i| i == index).expect("a task is its own peer");
+
+        let target = match to {
+            TaskMove::Up if at > 0 => at - 1,
+            TaskMove::Down if at + 1 < peers.len() => at + 1,
+            TaskMove::Top => 0,
+            TaskMove::Bottom => peers.len() - 1,
+            _ => at,
+        };
+        if target == at {
+            return false;
+        }
+        let task = self.incomplete_tasks.remove(index);
+        self.incomplete_tasks.insert(peers[target], task);
+        true
+    }
+
+    pub fn toggle_fold(&mut self, path: &TaskPath) {
+        let task = self.get_task_mut(path);
+        let folded = task.is_folded();
+        task.set_folded(!folded);
+    }
+
+    /// Folds or unfolds every task that has subtasks.
+    pub fn set_all_folded(&mut self, folded: bool) {
+        fn set(tasks: &mut [Task], folded: bool) {
+            for task in tasks {
+                task.set_folded(folded && !task.subtasks().is_empty());
+                set(task.subtasks_mut(), folded);
+            }
+        }
+        set(&mut self.incomplete_tasks, folded);
+        set(&mut self.complete_tasks, folded);
+    }
+
+    pub fn get_task(&self, path: &TaskPath) -> &Task {
+        let mut task = self.get_top_level_task(path.top());
+        for &index in &path.0[1..] {
+            task = &task.subtasks()[index];
+        }
+        task
+    }
+
+    fn get_task_mut(&mut self, path: &TaskPath) -> &mut Task {
+        let mut task = self.get_top_level_task_mut(path.top());
+        for &index in &path.0[1..] {
+            task = &mut task.subtasks_mut()[index];
+        }
+        task
+    }
+
+    fn get_top_level_task(&self, index: usize) -> &Task {
+        assert!(index < self.len());
+
+        if index < self.incomplete_tasks.len() {
+            &self.incomplete_tasks[index]
+        } else {
+            &self.complete_tasks[index - self.incomplete_tasks.len()]
+        }
+    }
+
+    fn get_top_level_task_mut(&mut self, index: usize) -> &mut Task {
+        assert!(index < self.len());
+
+        if index < self.incomplete_tasks.len() {
+            &mut self.incomplete_tasks[index]
+        } else {
+            &mut self.complete_tasks[index - self.incomplete_tasks.len()]
+        }
+    }
+
+    /// Inserts a top-level task after the one at `index` if it belongs to
+    /// the same list, or at the edge of its own list nearest to it.
+    fn insert_top_level_after(&mut self, index: usize, task: Task) {
+        let incomplete_len = self.incomplete_tasks.len();
+        if task.is_complete() {
+            let at = if index >= incomplete_len {
+                index - incomplete_len + 1
+            } else {
+                0
+            };
+            self.complete_tasks.insert(at, task);
+        } else {
+            let at = std::cmp::min(index + 1, incomplete_len);
+            self.incomplete_tasks.insert(at, task);
+        }
+    }
+
+    /// The indices of the top-level tasks in the order they are shown: the
+    /// incomplete tasks, by priority while sorting by it, then the complete
+    /// ones.
+    pub fn display_order(&self) -> Vec<usize> {
+        let mut order = (0..self.incomplete_tasks.len()).collect::<Vec<_>>();
+        if self.sort_by_priority {
+            // stable, so tasks of equal priority keep their manual order
+            order.sort_by_key(|&i| std::cmp::Reverse(self.incomplete_tasks[i].priority()));
+        }
+        order.extend(self.incomplete_tasks.len()..self.len());
+        order
+    }
+
+    pub fn get_complete_tasks(&self) -> &Vec<Task> {
+        &self.complete_tasks
+    }
+
+    pub fn get_incomplete_tasks(&self) -> &Vec<Task> {
+        &self.incomplete_tasks
+    }
+
+    /// Every task, subtasks included, depth-first.
+    pub fn all_tasks(&self) -> Vec<&Task> {
+        let mut tasks = Vec::new();
+        for task in self.incomplete_tasks.iter().chain(self.complete_tasks.iter()) {
+            task.walk(&mut |task| tasks.push(task));
+        }
+        tasks
+    }
+
+    pub fn edit_task(&mut self, path: &TaskPath, title: String) {
+        self.get_task_mut(path).set_title(title);
+    }
+
+    pub fn update_tags(&mut self, path: &TaskPath) {
+        self.get_task_mut(path).update_tags();
+    }
+
+    /// Re-reads the tags of every task, for files written before tags were
+    /// stored.
+    pub fn update_all_tags(&mut self) {
+        for task in self
+            .incomplete_tasks
+            .iter_mut()
+            .chain(self.complete_tasks.iter_mut())
+        {
+            task.update_all_tags();
+        }
+    }
+
+    /// Every tag used in the list with the number of tasks carrying it,
+    /// sorted by name.
+    pub fn tag_counts(&self) -> Vec<(String, usize)> {
+        let mut counts = std::collections::BTreeMap::new();
+        for task in self.all_tasks() {
+            for tag in task.tags() {
+                *counts.entry(tag.clone()).or_insert(0) += 1;
+            }
+        }
+        counts.into_iter().collect()
+    }
+
+    pub fn set_due(&mut self, path: &TaskPath, due: Option<Date>) {
+        self.get_task_mut(path).set_due(due);
+    }
+
+    pub fn set_notes(&mut self, path: &TaskPath, notes: String) {
+        self.get_task_mut(path).set_notes(notes);
+    }
+
+    pub fn raise_priority(&mut self, path: &TaskPath) {
+        self.get_task_mut(path).raise_priority();
+    }
+
+    pub fn lower_priority(&mut self, path: &TaskPath) {
+        self.get_task_mut(path).lower_priority();
+    }
+
+    pub fn is_sorted_by_priority(&self) -> bool {
+        self.sort_by_priority
+    }
+
+    pub fn set_sort_by_priority(&mut self, sort_by_priority: bool) {
+        self.sort_by_priority = sort_by_priority;
+    }
+
+    /// The path of the task with the given id.
+    pub fn find(&self, id: &str) -> Option<TaskPath> {
+        fn find_in(tasks: &[Task], id: &str, path: &[usize]) -> Option<TaskPath> {
+            for (index, task) in tasks.iter().enumerate() {
+                let mut task_path = path.to_vec();
+                task_path.push(index);
+                if task.id() == id {
+                    return Some(TaskPath(task_path));
+                }
+                if let Some(found) = find_in(task.subtasks(), id, &task_path) {
+                    return Some(found);
+                }
+            }
+            None
+        }
+
+        (0..self.len()).find_map(|index| {
+            let task = self.get_top_level_task(index);
+            if task.id() == id {
+                Some(TaskPath::root(index))
+            } else {
+                find_in(task.subtasks(), id, &[index])
+            }
+        })
+    }
+
+    /// The number of top-level tasks.
+    pub fn len(&self) -> usize {
+        self.incomplete_tasks.len() + self.complete_tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Merges a list changed here and in the file, see
+    /// [`merge_by_key`](merge::merge_by_key). `base` is the list as it was
+    /// before either change, if it existed then.
+    pub fn merge(base: Option<&TaskList>, local: &TaskList, remote: &TaskList) -> TaskList {
+        let tasks = |list: &TaskList| -> Vec<Task> {
+            list.incomplete_tasks
+                .iter()
+                .chain(&list.complete_tasks)
+                .cloned()
+                .collect()
+        };
+        let base_tasks = base.map(tasks).unwrap_or_default();
+        let (complete_tasks, incomplete_tasks) =
+            merge::merge_tasks(&base_tasks, &tasks(local), &tasks(remote))
+                .into_iter()
+                .partition(Task::is_complete);
+        let sort_by_priority = match base {
+            Some(base) if base.sort_by_priority == local.sort_by_priority => {
+                remote.sort_by_priority
+            }
+            _ => local.sort_by_priority,
+        };
+
+        let fields = match base {
+            Some(base) if base.fields == local.fields => remote.fields.clone(),
+            _ => local.fields.clone(),
+        };
+
+        TaskList {
+            name: local.name.clone(),
+            complete_tasks,
+            incomplete_tasks,
+            sort_by_priority,
+            fields,
+        }
+    }
+
+    pub fn get_field(&self, key: &str) -> Option<&serde_json::Value> {
+        self.fields.get(key)
+    }
+
+    pub fn set_field(&mut self, key: &str, value: serde_json::Value) {
+        self.fields.insert(key.to_string(), value);
+    }
+
+    /// Adds a task taken from another list: incomplete tasks go to the
+    /// bottom of the incomplete tasks, complete ones to the top of the
+    /// complete tasks.
+    pub fn push_task(&mut self, task: Task) {
+        if task.is_complete() {
+            self.complete_tasks.insert(0, task);
+        } else {
+            self.incomplete_tasks.push(task);
+        }
+    }
+}
@@ -1,20 +1,47 @@
-use crate::todo_serializer::TodoSerializer;
+use crate::merge;
+use crate::task_list::TaskList;
+use crate::todo_serializer::{FileLock, LockError, TodoSerializer};
 use serde::{Deserialize, Serialize};
 
+/// The whole document: every task list and which one is open.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Todo {
-    complete_tasks: Vec<String>,
-    incomplete_tasks: Vec<String>,
+    lists: Vec<TaskList>,
+    #[serde(default)]
+    active_list: usize,
     #[serde(skip)]
     serializer: Option<Box<dyn TodoSerializer>>,
 }
 
+/// The task data of a [`Todo`], detached from its serializer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TodoSnapshot {
+    lists: Vec<TaskList>,
+    active_list: usize,
+}
+
+pub const DEFAULT_LIST_NAME: &str = "Tasks";
+
+impl Default for Todo {
+    fn default() -> Self {
+        Self {
+            lists: vec![TaskList::new(DEFAULT_LIST_NAME.to_string())],
+            active_list: 0,
+            serializer: None,
+        }
+    }
+}
+
 impl Todo {
     pub fn new() -> Todo {
+        Todo::default()
+    }
+
+    /// A todo holding `lists`, the first one open.
+    pub fn with_lists(lists: Vec<TaskList>) -> Todo {
         Todo {
-            complete_tasks: Vec::new(),
-            incomplete_tasks: Vec::new(),
-            serializer: None,
+            lists,
+            ..Todo::default()
         }
     }
 
@@ -24,59 +51,75 @@ impl Todo {
         todo
     }
 
-    pub fn add_task(&mut self, index: usize, task: String) {
-        assert!(index <= self.incomplete_tasks.len());
+    pub fn active_list(&self) -> &TaskList {
+        &self.lists[self.active_list]
+    }
 
-        self.incomplete_tasks.insert(index, task);
+    pub fn active_list_mut(&mut self) -> &mut TaskList {
+        &mut self.lists[self.active_list]
     }
 
-    pub fn toggle_task(&mut self, index: usize) {
-        assert!(index < self.len());
+    pub fn active_list_index(&self) -> usize {
+        self.active_list
+    }
 
-        if index < self.incomplete_tasks.len() {
-            let task = self.incomplete_tasks.remove(index);
-            self.complete_tasks.insert(0, task);
-        } else {
-            let task = self
-                .complete_tasks
-                .remove(index - self.incomplete_tasks.len());
-            self.incomplete_tasks.push(task);
-        }
+    pub fn get_lists(&self) -> &Vec<TaskList> {
+        &self.lists
     }
 
-    pub fn delete_task(&mut self, index: usize) {
-        if index < self.incomplete_tasks.len() {
-            self.incomplete_tasks.remove(index);
-        } else {
-            self.complete_tasks
-                .remove(index - self.incomplete_tasks.len());
-        }
+    pub fn get_list_mut(&mut self, index: usize) -> &mut TaskList {
+        &mut self.lists[index]
     }
 
-    pub fn get_complete_tasks(&self) -> &Vec<String> {
-        &self.complete_tasks
+    pub fn set_active_list(&mut self, index: usize) {
+        assert!(index < self.lists.len());
+
+        self.active_list = index;
     }
 
-    pub fn get_incomplete_tasks(&self) -> &Vec<String> {
-        &self.incomplete_tasks
+    /// The index of the list with the given name, ignoring case.
+    pub fn find_list(&self, name: &str) -> Option<usize> {
+        self.lists
+            .iter()
+            .position(|list| list.get_name().eq_ignore_ascii_case(name))
     }
 
-    pub fn edit_task(&mut self, index: usize, task: String) {
-        assert!(index < self.len());
+    /// Adds an empty list right after the active one and opens it.
+    pub fn add_list(&mut self, name: String) {
+        self.active_list += 1;
+        self.lists.insert(self.active_list, TaskList::new(name));
+    }
 
-        if index < self.incomplete_tasks.len() {
-            self.incomplete_tasks[index] = task;
-        } else {
-            self.complete_tasks[index - self.incomplete_tasks.len()] = task;
+    /// Removes the active list and opens the one before it. The last
+    /// remaining list cannot be removed.
+    pub fn delete_active_list(&mut self) -> bool {
+        if self.lists.len() == 1 {
+            return false;
         }
+
+        self.lists.remove(self.active_list);
+        self.active_list = self.active_list.saturating_sub(1);
+        true
     }
 
-    pub fn len(&self) -> usize {
-        self.incomplete_tasks.len() + self.complete_tasks.len()
+    pub fn snapshot(&self) -> TodoSnapshot {
+        TodoSnapshot {
+            lists: self.lists.clone(),
+            active_list: self.active_list,
+        }
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
+    pub fn restore(&mut self, snapshot: TodoSnapshot) {
+        self.lists = snapshot.lists;
+        self.active_list = snapshot.active_list;
+    }
+
+    /// Locks the file the todo is saved to, if it has one.
+    pub fn lock(&self, take_over: bool) -> Result<Option<FileLock>, LockError> {
+        match &self.serializer {
+            Some(serializer) => serializer.lock(take_over),
+            None => Ok(None),
+        }
     }
 
     pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
@@ -89,9 +132,43 @@ impl Todo {
     pub fn load(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         if let Some(serializer) = &self.serializer {
             let todo = serializer.load()?;
-            self.complete_tasks = todo.complete_tasks;
-            self.incomplete_tasks = todo.incomplete_tasks;
+            self.lists = todo.lists;
+            self.active_list = todo.active_list;
+            if self.lists.is_empty() {
+                self.lists.push(TaskList::new(DEFAULT_LIST_NAME.to_string()));
+            }
+            self.active_list = self.active_list.min(self.lists.len() - 1);
+            for list in &mut self.lists {
+                list.update_all_tags();
+            }
         }
         Ok(())
     }
 }
+
+impl TodoSnapshot {
+    /// Merges the lists changed here with those changed in the file, given
+    /// how both were before. Lists are matched by name. The open list stays
+    /// open if it is still there.
+    pub fn merge(base: &TodoSnapshot, local: &TodoSnapshot, remote: &TodoSnapshot) -> TodoSnapshot {
+        let mut lists = merge::merge_by_key(
+            &base.lists,
+            &local.lists,
+            &remote.lists,
+            TaskList::get_name,
+            TaskList::merge,
+        );
+        if lists.is_empty() {
+            lists.push(TaskList::new(DEFAULT_LIST_NAME.to_string()));
+        }
+        let open = local.lists[local.active_list].get_name();
+        let active_list = lists
+            .iter()
+            .position(|list| list.get_name() == open)
+            .unwrap_or(0);
+        TodoSnapshot {
+            lists,
+            active_list,
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use time::format_description::FormatItem;
+use time::{macros::format_description, OffsetDateTime, PrimitiveDateTime};
+
+/// How many backups of a todo file are kept. The oldest go first.
+pub const MAX_BACKUPS: usize = 10;
+
+/// A new backup is only taken when the newest one is older than this, so
+/// that the backups reach further back than the last few keystrokes.
+const BACKUP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Backups are named after when they were taken, in UTC, and keep the
+/// extension of the todo file so they can be read the same way.
+const NAME_FORMAT: &[FormatItem] =
+    format_description!("[year]-[month]-[day]T[hour]-[minute]-[second]Z");
+
+/// A copy of the todo file kept in its backup directory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Backup {
+    pub path: PathBuf,
+    /// When the backup was taken, as written in its file name.
+    pub taken: OffsetDateTime,
+}
+
+/// Writes `contents` to a temp file next to `path`, flushes it to disk and
+/// renames it over `path`, so the file is either the old or the new one even
+/// if writing fails halfway. The directory is flushed too, so that the
+/// rename survives a crash. The new file gets the permissions of the old
+/// one, and a symlink is left in place with the file it points to replaced.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let path = &follow_links(path)?;
+    let dir = parent_dir(path);
+    // the data directory may not exist yet on the first save
+    fs::create_dir_all(dir)?;
+    let permissions = fs::metadata(path).ok().map(|metadata| metadata.permissions());
+    let (mut file, temp_path) = create_temp_file(dir, &file_name(path))?;
+
+    let result = (|| {
+        if let Some(permissions) = permissions {
+            file.set_permissions(permissions)?;
+        }
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result?;
+    sync_dir(dir)
+}
+
+/// Creates a temp file next to where `file_name` goes, under a name no
+/// other save uses at the same time.
+fn create_temp_file(dir: &Path, file_name: &str) -> io::Result<(File, PathBuf)> {
+    loop {
+        let path = dir.join(format!(".{}.{:016x}.tmp", file_name, rand::random::<u64>()));
+        // never follows a symlink, nor opens a file that is already there
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((file, path)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// The file `path` ends up at through any symlinks, which may not exist
+/// yet.
+fn follow_links(path: &Path) -> io::Result<PathBuf> {
+    let mut path = path.to_path_buf();
+    // as many as Linux follows before giving up on a loop
+    for _ in 0..40 {
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                path = parent_dir(&path).join(fs::read_link(&path)?);
+            }
+            _ => return Ok(path),
+        }
+    }
+    Err(io::Error::other(format!(
+        "Too many levels of symbolic links: {}",
+        path.display()
+    )))
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+/// Directories can't be opened as files here, and renames are journaled.
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Copies `path` into its backup directory if the newest backup is old
+/// enough, then drops all but the newest [`MAX_BACKUPS`]. A backup that
+/// can't be dropped is left for the next time, rather than failing the
+/// save.
+pub fn rotate(path: &Path) -> io::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let recent = backups(path)
+        .first()
+        .and_then(|backup| fs::metadata(&backup.path).ok()?.modified().ok())
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < BACKUP_INTERVAL);
+    if recent {
+        return Ok(());
+    }
+
+    let dir = backup_dir(path);
+    fs::create_dir_all(&dir)?;
+    let name = OffsetDateTime::now_utc()
+        .format(NAME_FORMAT)
+        .map_err(io::Error::other)?;
+    fs::copy(path, dir.join(format!("{}{}", name, extension(path))))?;
+
+    for backup in backups(path).into_iter().skip(MAX_BACKUPS) {
+        let _ = fs::remove_file(backup.path);
+    }
+    Ok(())
+}
+
+/// The backups of `path`, newest first.
+pub fn backups(path: &Path) -> Vec<Backup> {
+    let Ok(entries) = fs::read_dir(backup_dir(path)) else {
+        return Vec::new();
+    };
+    let extension = extension(path);
+
+    let mut backups = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            let name = file_name.strip_suffix(&extension)?;
+            let taken = PrimitiveDateTime::parse(name, NAME_FORMAT).ok()?.assume_utc();
+            Some(Backup {
+                path: entry.path(),
+                taken,
+            })
+        })
+        .collect::<Vec<_>>();
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.taken));
+    backups
+}
+
+/// Puts `backup` in place of `path`. What `path` held is kept next to it
+/// as `todo.json.before-recover`, since it may hold changes made after the
+/// backup.
+pub fn restore(path: &Path, backup: &Backup) -> io::Result<()> {
+    let contents = fs::read(&backup.path)?;
+    if path.exists() {
+        let kept = parent_dir(path).join(format!("{}.before-recover", file_name(path)));
+        fs::copy(path, kept)?;
+    }
+    write_atomic(path, &contents)
+}
+
+/// Where the backups of `path` are kept: `.todo.json.backups` next to
+/// `todo.json`.
+pub fn backup_dir(path: &Path) -> PathBuf {
+    parent_dir(path).join(format!(".{}.backups", file_name(path)))
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+/// The extension of `path` with its dot, or nothing.
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory of its own in the system's temp dir, removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> TempDir {
+            let path = std::env::temp_dir()
+                .join(format!("todo-term-test-{:016x}", rand::random::<u64>()));
+            fs::create_dir(&path).unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Puts a backup of `path` taken `age` ago in its backup directory.
+    fn old_backup(path: &Path, age: Duration) -> PathBuf {
+        let taken = SystemTime::now() - age;
+        let name = OffsetDateTime::from(taken).format(NAME_FORMAT).unwrap();
+        let dir = backup_dir(path);
+        fs::create_dir_all(&dir).unwrap();
+        let backup = dir.join(format!("{}{}", name, extension(path)));
+        fs::write(&backup, "old").unwrap();
+        File::options()
+            .write(true)
+            .open(&backup)
+            .unwrap()
+            .set_modified(taken)
+            .unwrap();
+        backup
+    }
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn write_atomic_replaces_the_file() {
+        let dir = TempDir::new();
+        let path = dir.0.join("todo.json");
+        write_atomic(&path, b"one").unwrap();
+        write_atomic(&path, b"two").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "two");
+        // no temp file is left behind
+        assert_eq!(fs::read_dir(&dir.0).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_atomic_keeps_permissions_and_symlinks() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = TempDir::new();
+        let target = dir.0.join("todo.json");
+        fs::write(&target, "one").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o600)).unwrap();
+        let link = dir.0.join("link.json");
+        symlink("todo.json", &link).unwrap();
+
+        write_atomic(&link, b"two").unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "two");
+        let mode = fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn rotate_backs_up_at_most_every_ten_minutes() {
+        let dir = TempDir::new();
+        let path = dir.0.join("todo.json");
+        rotate(&path).unwrap();
+        assert!(backups(&path).is_empty());
+
+        fs::write(&path, "new").unwrap();
+        old_backup(&path, 5 * MINUTE);
+        rotate(&path).unwrap();
+        assert_eq!(backups(&path).len(), 1);
+
+        let dir = TempDir::new();
+        let path = dir.0.join("todo.json");
+        fs::write(&path, "new").unwrap();
+        old_backup(&path, 11 * MINUTE);
+        rotate(&path).unwrap();
+        let backups = backups(&path);
+        assert_eq!(backups.len(), 2);
+        assert_eq!(fs::read_to_string(&backups[0].path).unwrap(), "new");
+    }
+
+    #[test]
+    fn rotate_keeps_only_the_newest_backups() {
+        let dir = TempDir::new();
+        let path = dir.0.join("todo.md");
+        fs::write(&path, "new").unwrap();
+        for hours in 1..MAX_BACKUPS as u32 {
+            old_backup(&path, hours * 60 * MINUTE);
+        }
+        let oldest = old_backup(&path, MAX_BACKUPS as u32 * 60 * MINUTE);
+
+        rotate(&path).unwrap();
+        let backups = backups(&path);
+        assert_eq!(backups.len(), MAX_BACKUPS);
+        assert_eq!(fs::read_to_string(&backups[0].path).unwrap(), "new");
+        assert!(!oldest.exists());
+    }
+
+    #[test]
+    fn backups_are_listed_newest_first() {
+        let dir = TempDir::new();
+        let path = dir.0.join("todo.json");
+        let older = old_backup(&path, 60 * MINUTE);
+        let newer = old_backup(&path, 30 * MINUTE);
+        // neither is a backup of todo.json
+        old_backup(&dir.0.join("todo.md"), 10 * MINUTE);
+        fs::write(backup_dir(&path).join("notes.json"), "").unwrap();
+
+        let paths = backups(&path).into_iter().map(|backup| backup.path).collect::<Vec<_>>();
+        assert_eq!(paths, vec![newer, older]);
+    }
+
+    #[test]
+    fn restore_keeps_what_it_replaces() {
+        let dir = TempDir::new();
+        let path = dir.0.join("todo.json");
+        fs::write(&path, "new").unwrap();
+        old_backup(&path, 60 * MINUTE);
+
+        restore(&path, &backups(&path)[0]).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+        let kept = dir.0.join("todo.json.before-recover");
+        assert_eq!(fs::read_to_string(kept).unwrap(), "new");
+    }
+}
@@ -0,0 +1,18 @@
+use std::error::Error;
+use std::path::Path;
+
+use super::{backup, json_serializer, markdown_serializer};
+use crate::todo::Todo;
+
+/// Writes `todo` to `path`, as a Markdown checklist with a heading per list
+/// for `.md` and `.markdown` files and in the regular save format for
+/// anything else. Unlike a save, this keeps no backups of what was there.
+pub fn export(todo: &Todo, path: &str) -> Result<(), Box<dyn Error>> {
+    let contents = if markdown_serializer::is_markdown(path) {
+        markdown_serializer::to_markdown(todo)
+    } else {
+        json_serializer::to_json(todo)?
+    };
+    backup::write_atomic(Path::new(path), contents.as_bytes())?;
+    Ok(())
+}
@@ -1,4 +1,6 @@
-use super::TodoSerializer;
+use std::path::Path;
+
+use super::{backup, migrations, FileLock, LockError, TodoSerializer};
 
 pub struct JsonSerializer {
     path: String,
@@ -10,17 +12,35 @@ impl JsonSerializer {
     }
 }
 
+/// The todo as the save format, tagged with its version.
+pub fn to_json(todo: &crate::todo::Todo) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&migrations::wrap(todo))
+}
+
 impl TodoSerializer for JsonSerializer {
+    /// Backs up the file as it was, then replaces it atomically.
     fn save(&self, todo: &crate::todo::Todo) -> Result<(), Box<dyn std::error::Error>> {
-        let contents = serde_json::to_string_pretty(todo)?;
-        std::fs::write(&self.path, contents)?;
+        let contents = to_json(todo)?;
+        let path = Path::new(&self.path);
+        backup::rotate(path)?;
+        backup::write_atomic(path, contents.as_bytes())?;
         Ok(())
     }
 
     fn load(&self) -> Result<crate::todo::Todo, Box<dyn std::error::Error>> {
-        let contents = std::fs::read_to_string(&self.path)?;
-        let todo = serde_json::from_str(&contents)?;
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(crate::todo::Todo::new());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let document = serde_json::from_str(&contents)?;
+        let todo = serde_json::from_value(migrations::migrate(document)?)?;
         Ok(todo)
     }
-}
 
+    fn lock(&self, take_over: bool) -> Result<Option<FileLock>, LockError> {
+        FileLock::acquire(Path::new(&self.path), take_over).map(Some)
+    }
+}
@@ -0,0 +1,287 @@
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// An advisory lock on a todo file, held as long as this lives: a
+/// `.todo.json.lock` file next to it holding the process id. Other
+/// todo-term processes respect it, nothing else does.
+#[derive(Debug)]
+pub struct FileLock {
+    path: PathBuf,
+}
+
+#[derive(Debug)]
+pub enum LockError {
+    /// Another running process holds the lock.
+    Held(u32),
+    Io(io::Error),
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LockError::Held(pid) => write!(f, "open in another todo-term (pid {})", pid),
+            LockError::Io(e) => write!(f, "can't lock: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LockError {}
+
+impl From<io::Error> for LockError {
+    fn from(e: io::Error) -> Self {
+        LockError::Io(e)
+    }
+}
+
+impl FileLock {
+    /// Locks `path`. A lock left behind by a process that is gone is taken
+    /// over, one held by a running process only with `take_over`.
+    pub fn acquire(path: &Path, take_over: bool) -> Result<FileLock, LockError> {
+        let lock = FileLock {
+            path: lock_path(path),
+        };
+        let pid = std::process::id();
+        if let Some(dir) = lock.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // each time around, a lock is taken, refused or removed, unless
+        // another process takes it over at the same time
+        loop {
+            match create_with_pid(&lock.path, pid) {
+                Ok(()) => return Ok(lock),
+                Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e.into()),
+                Err(_) => {}
+            }
+
+            let holder = lock.holder();
+            match holder {
+                Some(holder) if holder == pid => return Ok(lock),
+                Some(holder) if is_running(holder) && !take_over => {
+                    return Err(LockError::Held(holder));
+                }
+                _ => remove_if_held_by(&lock.path, holder)?,
+            }
+        }
+    }
+
+    /// Fails if another process has taken the lock over since.
+    pub fn check(&self) -> Result<(), LockError> {
+        match self.holder() {
+            Some(holder) if holder != std::process::id() => Err(LockError::Held(holder)),
+            _ => Ok(()),
+        }
+    }
+
+    /// The process id in the lock file.
+    fn holder(&self) -> Option<u32> {
+        read_pid(&self.path)
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // a lock taken over by another process is theirs to remove
+        if self.check().is_ok() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Creates the lock file already holding `pid`, so that another process
+/// never reads it empty: the pid goes into a file of our own first, which
+/// is then linked into place. Linking fails if the lock file exists, like
+/// `create_new` does.
+fn create_with_pid(path: &Path, pid: u32) -> io::Result<()> {
+    let temp_path = path.with_file_name(format!(
+        "{}.{}",
+        path.file_name().unwrap_or_default().to_string_lossy(),
+        pid
+    ));
+    let result = (|| {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&temp_path)?;
+        write!(file, "{}", pid)?;
+        file.sync_all()?;
+        match fs::hard_link(&temp_path, path) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(e),
+            // a file system without hard links, where the pid is
+            // written right after creating the file instead
+            Err(_) => {
+                let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+                write!(file, "{}", pid)
+            }
+            Ok(()) => Ok(()),
+        }
+    })();
+    let _ = fs::remove_file(&temp_path);
+    result
+}
+
+/// Removes the lock file at `path` if it still holds `holder`. It is moved
+/// aside to be looked at, so that a lock another process took in the
+/// meantime isn't removed: that one is put back.
+fn remove_if_held_by(path: &Path, holder: Option<u32>) -> io::Result<()> {
+    let aside = path.with_file_name(format!(
+        "{}.{}.old",
+        path.file_name().unwrap_or_default().to_string_lossy(),
+        std::process::id()
+    ));
+    match fs::rename(path, &aside) {
+        // removed by another process already
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        result => result?,
+    }
+    if read_pid(&aside) != holder {
+        // fails if yet another process has locked it since, which is
+        // then theirs
+        let _ = fs::hard_link(&aside, path);
+    }
+    let _ = fs::remove_file(&aside);
+    Ok(())
+}
+
+fn read_pid(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn lock_path(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.lock", file_name))
+}
+
+/// Whether a process with this id exists. Without a way to tell, it is
+/// assumed to, so a lock is never taken from a running process.
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // signal 0 only checks whether the process could be signalled
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(windows)]
+fn is_running(pid: u32) -> bool {
+    use winapi::shared::winerror::ERROR_ACCESS_DENIED;
+    use winapi::um::errhandlingapi::GetLastError;
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::minwinbase::STILL_ACTIVE;
+    use winapi::um::processthreadsapi::{GetExitCodeProcess, OpenProcess};
+    use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process.is_null() {
+            // only a process that exists can refuse to be looked at
+            return GetLastError() == ERROR_ACCESS_DENIED;
+        }
+        let mut exit_code: u32 = 0;
+        let queried = GetExitCodeProcess(process, &mut exit_code);
+        CloseHandle(process);
+        // an exited process can linger while others hold a handle to it
+        queried == 0 || exit_code == STILL_ACTIVE
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn is_running(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A todo file in the system's temp dir that nothing else uses, and
+    /// its lock file, removed on drop.
+    struct TempTodo(PathBuf);
+
+    impl TempTodo {
+        fn new() -> TempTodo {
+            let name = format!("todo-term-test-{:016x}.json", rand::random::<u64>());
+            TempTodo(std::env::temp_dir().join(name))
+        }
+
+        fn lock_path(&self) -> PathBuf {
+            lock_path(&self.0)
+        }
+    }
+
+    impl Drop for TempTodo {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(self.lock_path());
+        }
+    }
+
+    /// No process has this id, as pids stay far below it.
+    const GONE: u32 = i32::MAX as u32;
+
+    #[test]
+    fn acquire_writes_the_pid_and_drop_removes_it() {
+        let todo = TempTodo::new();
+        let lock = FileLock::acquire(&todo.0, false).unwrap();
+        assert_eq!(read_pid(&todo.lock_path()), Some(std::process::id()));
+        assert!(lock.check().is_ok());
+
+        drop(lock);
+        assert!(!todo.lock_path().exists());
+    }
+
+    #[test]
+    fn a_lock_left_by_a_process_that_is_gone_is_taken_over() {
+        let todo = TempTodo::new();
+        fs::write(todo.lock_path(), GONE.to_string()).unwrap();
+
+        let _lock = FileLock::acquire(&todo.0, false).unwrap();
+        assert_eq!(read_pid(&todo.lock_path()), Some(std::process::id()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_lock_held_by_a_running_process_is_only_taken_when_asked() {
+        let todo = TempTodo::new();
+        // init, which always runs
+        fs::write(todo.lock_path(), "1").unwrap();
+
+        assert!(matches!(
+            FileLock::acquire(&todo.0, false),
+            Err(LockError::Held(1))
+        ));
+        assert_eq!(read_pid(&todo.lock_path()), Some(1));
+
+        let _lock = FileLock::acquire(&todo.0, true).unwrap();
+        assert_eq!(read_pid(&todo.lock_path()), Some(std::process::id()));
+    }
+
+    #[test]
+    fn a_lock_taken_over_is_left_to_its_new_holder() {
+        let todo = TempTodo::new();
+        let lock = FileLock::acquire(&todo.0, false).unwrap();
+        fs::write(todo.lock_path(), GONE.to_string()).unwrap();
+        assert!(matches!(lock.check(), Err(LockError::Held(GONE))));
+
+        drop(lock);
+        assert_eq!(read_pid(&todo.lock_path()), Some(GONE));
+    }
+
+    #[test]
+    fn a_lock_taken_since_it_was_read_is_not_removed() {
+        let todo = TempTodo::new();
+        fs::write(todo.lock_path(), "1").unwrap();
+
+        remove_if_held_by(&todo.lock_path(), Some(GONE)).unwrap();
+        assert_eq!(read_pid(&todo.lock_path()), Some(1));
+        remove_if_held_by(&todo.lock_path(), Some(1)).unwrap();
+        assert!(!todo.lock_path().exists());
+    }
+}
@@ -0,0 +1,844 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use regex::Regex;
+use serde_json::Value;
+use time::{macros::format_description, Date};
+
+use super::{backup, FileLock, LockError, TodoSerializer};
+use crate::due;
+use crate::task::{Priority, Task};
+use crate::task_list::TaskList;
+use crate::todo::{Todo, DEFAULT_LIST_NAME};
+
+/// The heading line a list was read from, such as `## Work`. Empty for
+/// the tasks before the first heading. Lists without it get a `#` heading.
+const HEADING_FIELD: &str = "markdown.heading";
+/// `\r\n` on the lists of a file with Windows line endings.
+const NEWLINE_FIELD: &str = "markdown.newline";
+/// False on the last list of a file that doesn't end with a line break.
+const FINAL_NEWLINE_FIELD: &str = "markdown.final_newline";
+/// Lines before the first heading that aren't tasks.
+const BEFORE_FIELD: &str = "markdown.before";
+/// Lines between a heading and its first task that aren't tasks.
+const INTRO_FIELD: &str = "markdown.intro";
+/// Lines after a task and its notes that aren't tasks.
+const AFTER_FIELD: &str = "markdown.after";
+/// The bullet of a task when it isn't `-`.
+const MARKER_FIELD: &str = "markdown.marker";
+/// The check mark of a complete task when it isn't `x`.
+const CHECK_FIELD: &str = "markdown.check";
+/// How far the bullet of a task is from its parent's bullet, or from the
+/// margin at the top, when that isn't as far as the parent's text.
+const INDENT_FIELD: &str = "markdown.indent";
+/// The text of an item as it was read, when todo-term would write it
+/// differently, such as `!high` before `due:`. It is written again as long
+/// as it reads back as the task.
+const TEXT_FIELD: &str = "markdown.text";
+
+/// Reads and writes a todo as GitHub-style task lists:
+///
+/// ```markdown
+/// # Work
+///
+/// - [ ] Ship the release #work due:2026-10-20 !high
+///   - [x] Write the changelog
+///
+/// # Home
+/// ```
+///
+/// Headings are lists, nested items are subtasks, lines indented under an
+/// item are its notes. Everything else is kept where it was, so the file
+/// can hold prose, and a file read and written again is left as it was.
+/// Complete tasks are written after the incomplete ones of
+/// their list, as todo-term shows them. Task ids are derived from the
+/// titles, as Markdown has no place for them. A task whose id can't be
+/// derived that way, like one added in todo-term, gets it written in an
+/// HTML comment at the end of its line.
+pub struct MarkdownSerializer {
+    path: String,
+}
+
+impl MarkdownSerializer {
+    pub fn new(path: String) -> MarkdownSerializer {
+        MarkdownSerializer { path }
+    }
+}
+
+impl TodoSerializer for MarkdownSerializer {
+    fn save(&self, todo: &Todo) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Path::new(&self.path);
+        backup::rotate(path)?;
+        backup::write_atomic(path, to_markdown(todo).as_bytes())?;
+        Ok(())
+    }
+
+    fn load(&self) -> Result<Todo, Box<dyn std::error::Error>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(contents) => Ok(from_markdown(&contents)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Todo::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn lock(&self, take_over: bool) -> Result<Option<FileLock>, LockError> {
+        FileLock::acquire(Path::new(&self.path), take_over).map(Some)
+    }
+}
+
+/// Whether `path` is a Markdown file, by its extension.
+pub fn is_markdown(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            ["md", "markdown"].contains(&extension.to_ascii_lowercase().as_str())
+        })
+}
+
+pub fn to_markdown(todo: &Todo) -> String {
+    let heading_regex = heading_regex();
+    let item_regex = item_regex();
+    let mut out = String::new();
+    // whether the last line written is blank, if there is one
+    let mut last_blank = None;
+    let mut final_newline = true;
+    for list in todo.get_lists() {
+        let mut lines = field_lines(list.get_field(BEFORE_FIELD));
+        let heading = match list.get_field(HEADING_FIELD).and_then(Value::as_str) {
+            Some("") => None,
+            Some(heading) => Some(match heading_regex.captures(heading) {
+                Some(captures) if &captures[2] == list.get_name() => heading.to_string(),
+                Some(captures) => format!("{} {}", &captures[1], list.get_name()),
+                None => format!("# {}", list.get_name()),
+            }),
+            // a list made in todo-term
+            None => {
+                let last_blank = lines.last().map(|line| line.trim().is_empty()).or(last_blank);
+                if last_blank == Some(false) {
+                    lines.push(String::new());
+                }
+                Some(format!("# {}", list.get_name()))
+            }
+        };
+        // the name as it will be read back, which the ids are derived from
+        let name = heading.as_ref().map(|heading| match heading_regex.captures(heading) {
+            Some(captures) => captures[2].to_string(),
+            None => list.get_name().to_string(),
+        });
+        let mut ids = Ids::new(name);
+        if let Some(heading) = heading {
+            lines.push(heading);
+            if list.get_field(HEADING_FIELD).is_none() {
+                lines.push(String::new());
+            }
+        }
+        lines.extend(field_lines(list.get_field(INTRO_FIELD)));
+        let mut writer = Writer {
+            lines,
+            item: &item_regex,
+            ids: &mut ids,
+            parents: Vec::new(),
+        };
+        writer.write_tasks(
+            list.get_incomplete_tasks().iter().chain(list.get_complete_tasks()),
+            None,
+        );
+
+        let newline = list
+            .get_field(NEWLINE_FIELD)
+            .and_then(Value::as_str)
+            .unwrap_or("\n");
+        if let Some(line) = writer.lines.last() {
+            last_blank = Some(line.trim().is_empty());
+        }
+        for line in writer.lines {
+            out.push_str(&line);
+            out.push_str(newline);
+        }
+        final_newline = list.get_field(FINAL_NEWLINE_FIELD) != Some(&Value::Bool(false));
+    }
+
+    if out.is_empty() {
+        out.push('\n');
+    }
+    if !final_newline {
+        let end = out.strip_suffix('\n').unwrap_or(&out);
+        let end = end.strip_suffix('\r').unwrap_or(end).len();
+        out.truncate(end);
+    }
+    out
+}
+
+/// Writes the tasks of a list.
+struct Writer<'a> {
+    lines: Vec<String>,
+    item: &'a Regex,
+    ids: &'a mut Ids,
+    /// The titles of the ancestors of the tasks being written, as they will
+    /// be read back.
+    parents: Vec<String>,
+}
+
+impl Writer<'_> {
+    /// Writes `tasks` as siblings below the item whose bullet and text
+    /// start at `parent`, or at the top.
+    fn write_tasks<'t>(
+        &mut self,
+        tasks: impl Iterator<Item = &'t Task>,
+        parent: Option<(usize, usize)>,
+    ) {
+        let (base, default, min) = match parent {
+            Some((indent, content)) => (indent, content - indent, indent + 1),
+            None => (0, 0, 0),
+        };
+        let mut previous = usize::MAX;
+        for task in tasks {
+            let offset = task
+                .get_field(INDENT_FIELD)
+                .and_then(Value::as_u64)
+                .map_or(default, |offset| offset as usize);
+            // further in than the sibling before would make it its subtask
+            let indent = (base + offset).min(previous).max(min);
+            previous = indent;
+            self.write_task(task, indent);
+        }
+    }
+
+    fn write_task(&mut self, task: &Task, indent: usize) {
+        let marker = task
+            .get_field(MARKER_FIELD)
+            .and_then(Value::as_str)
+            .unwrap_or("-");
+        let check = match task.is_complete() {
+            true => task.get_field(CHECK_FIELD).and_then(Value::as_str).unwrap_or("x"),
+            false => " ",
+        };
+        let title_due_priority = (task.title().to_string(), task.due(), task.priority());
+        let mut text = match task.get_field(TEXT_FIELD).and_then(Value::as_str) {
+            Some(text) if parse_text(text) == title_due_priority => text.to_string(),
+            _ => item_text(task),
+        };
+        let title = title_due_priority.0;
+        if self.ids.next(&self.parents, &title) != task.id() {
+            text.push_str(&format!(" {}{}{}", ID_COMMENT_START, task.id(), ID_COMMENT_END));
+        }
+        self.lines
+            .push(format!("{}{} [{}] {}", " ".repeat(indent), marker, check, text));
+
+        let content = indent + marker.len() + 1;
+        for note in escape_notes(task.notes(), self.item) {
+            match note.as_str() {
+                "" => self.lines.push(String::new()),
+                note => self.lines.push(format!("{}{}", " ".repeat(content), note)),
+            }
+        }
+        self.lines.extend(field_lines(task.get_field(AFTER_FIELD)));
+        self.parents.push(title);
+        self.write_tasks(task.subtasks().iter(), Some((indent, content)));
+        self.parents.pop();
+    }
+}
+
+/// The text of an item for `task`. A title that ends in something like
+/// `!high` gets it escaped, so that it isn't read as the priority.
+fn item_text(task: &Task) -> String {
+    let title = task.title();
+    let last = title.rfind(' ').map_or(0, |space| space + 1);
+    let mut text = if is_token(title[last..].trim_start_matches('\\')) {
+        format!("{}\\{}", &title[..last], &title[last..])
+    } else {
+        title.to_string()
+    };
+    if let Some(date) = task.due() {
+        text.push_str(&format!(" due:{}", due::format_date(date)));
+    }
+    match task.priority() {
+        Some(Priority::High) => text.push_str(" !high"),
+        Some(Priority::Medium) => text.push_str(" !medium"),
+        Some(Priority::Low) => text.push_str(" !low"),
+        None => {}
+    }
+    text
+}
+
+/// The lines of `notes`, with a backslash before the ones that would be
+/// read as an item, or as a code fence that is never closed.
+fn escape_notes(notes: &str, item: &Regex) -> Vec<String> {
+    let lines = notes.lines().collect::<Vec<_>>();
+    let mut in_code = false;
+    let mut escaped = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let escape = if is_fence(line) {
+            let closed = in_code || lines[i + 1..].iter().any(|line| is_fence(line));
+            in_code = closed && !in_code;
+            !closed
+        } else {
+            !in_code && needs_escape(line, item)
+        };
+        escaped.push(match escape {
+            true => {
+                let spaces = line.len() - line.trim_start_matches(' ').len();
+                format!("{}\\{}", &line[..spaces], &line[spaces..])
+            }
+            false => line.to_string(),
+        });
+    }
+    escaped
+}
+
+/// Takes out the backslash [`escape_notes`] put before a note line.
+fn unescape_note(line: &str, item: &Regex) -> String {
+    let spaces = line.len() - line.trim_start_matches(' ').len();
+    if line[spaces..].starts_with('\\') && needs_escape(line, item) {
+        format!("{}{}", &line[..spaces], &line[spaces + 1..])
+    } else {
+        line.to_string()
+    }
+}
+
+/// Whether a note line, with any backslashes at its start taken out,
+/// would be read as an item or a code fence.
+fn needs_escape(line: &str, item: &Regex) -> bool {
+    let spaces = line.len() - line.trim_start_matches(' ').len();
+    let unescaped = format!("{}{}", &line[..spaces], line[spaces..].trim_start_matches('\\'));
+    is_fence(&unescaped) || item.is_match(&unescaped)
+}
+
+fn is_fence(line: &str) -> bool {
+    line.trim_start().starts_with("```")
+}
+
+fn field_lines(value: Option<&Value>) -> Vec<String> {
+    match value.and_then(Value::as_array) {
+        Some(lines) => lines
+            .iter()
+            .filter_map(|line| line.as_str().map(str::to_string))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// A task being read, with where its lines go.
+struct OpenTask {
+    task: Task,
+    /// Where the item's bullet starts.
+    indent: usize,
+    /// Where its text starts. Lines indented this far right below it are
+    /// its notes.
+    content: usize,
+    notes: Vec<String>,
+    after: Vec<String>,
+}
+
+/// What lines that aren't tasks belong to.
+enum Prose {
+    Before,
+    Intro,
+    /// Notes of the last task, while they continue.
+    Notes,
+    After,
+}
+
+/// Derives the ids of the tasks of a list, in the order they are in the
+/// file. The id stays the same every time the file is read, as long as the
+/// task keeps its list, parents and title.
+struct Ids {
+    list: Option<String>,
+    /// How often each title was seen in the list, to tell apart the ids of
+    /// tasks with the same title.
+    seen: HashMap<Vec<String>, usize>,
+}
+
+impl Ids {
+    fn new(list: Option<String>) -> Ids {
+        Ids {
+            list,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// The id of the next task titled `title`, below `parents`.
+    fn next(&mut self, parents: &[String], title: &str) -> String {
+        let mut path = parents.to_vec();
+        path.push(title.to_string());
+        let count = self.seen.entry(path.clone()).or_default();
+        *count += 1;
+
+        // FNV-1a, spelled out so that ids don't change with the Rust version
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= u64::from(*byte);
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        };
+        match &self.list {
+            Some(list) => {
+                write(b"#");
+                write(list.as_bytes());
+            }
+            None => write(b"-"),
+        }
+        for title in &path {
+            write(b"\n");
+            write(title.as_bytes());
+        }
+        write(format!("\n{}", count).as_bytes());
+        format!("{:016x}", hash)
+    }
+}
+
+/// Wraps the id of a task that can't be derived from its title.
+const ID_COMMENT_START: &str = "<!-- id:";
+const ID_COMMENT_END: &str = " -->";
+
+fn item_regex() -> Regex {
+    Regex::new(r"^( *)([-*+]|\d+[.)]) \[([ xX])\](?: (.*))?$").expect("valid regex")
+}
+
+fn heading_regex() -> Regex {
+    Regex::new(r"^(#{1,6}) +(.*?)(?: +#+)? *$").expect("valid regex")
+}
+
+struct Reader {
+    item: Regex,
+    heading: Regex,
+    lists: Vec<TaskList>,
+    /// Tasks of the current list, finished ones.
+    tasks: Vec<Task>,
+    /// The tasks whose subtasks are still being read, outermost first.
+    open: Vec<OpenTask>,
+    name: Option<String>,
+    heading_field: String,
+    before: Vec<String>,
+    intro: Vec<String>,
+    prose: Prose,
+    /// Blank lines that may still turn out to be inside notes.
+    blanks: usize,
+    in_code: bool,
+    ids: Ids,
+    /// `\r\n` when the file has Windows line endings.
+    newline: Option<&'static str>,
+}
+
+pub fn from_markdown(contents: &str) -> Todo {
+    let mut reader = Reader {
+        item: item_regex(),
+        heading: heading_regex(),
+        lists: Vec::new(),
+        tasks: Vec::new(),
+        open: Vec::new(),
+        name: None,
+        heading_field: String::new(),
+        before: Vec::new(),
+        intro: Vec::new(),
+        prose: Prose::Before,
+        blanks: 0,
+        in_code: false,
+        ids: Ids::new(None),
+        newline: contents.contains("\r\n").then_some("\r\n"),
+    };
+    for line in contents.lines() {
+        reader.read_line(line);
+    }
+    reader.finish_list(true);
+
+    let mut lists = reader.lists;
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        if let Some(list) = lists.last_mut() {
+            list.set_field(FINAL_NEWLINE_FIELD, Value::Bool(false));
+        }
+    }
+    Todo::with_lists(lists)
+}
+
+impl Reader {
+    fn read_line(&mut self, line: &str) {
+        if line.trim_start().starts_with("```") {
+            self.in_code = !self.in_code;
+            self.read_prose(line);
+            return;
+        }
+        if self.in_code {
+            self.read_prose(line);
+            return;
+        }
+
+        if let Some(captures) = self.heading.captures(line) {
+            self.finish_list(false);
+            self.name = Some(captures[2].to_string());
+            self.ids = Ids::new(self.name.clone());
+            self.heading_field = line.to_string();
+            self.prose = Prose::Intro;
+            return;
+        }
+
+        if let Some(captures) = self.item.captures(line) {
+            self.flush_blanks();
+            let indent = captures[1].len();
+            let marker = captures[2].to_string();
+            let check = captures[3].to_string();
+            let text = captures.get(4).map_or("", |text| text.as_str()).to_string();
+            self.close_tasks(indent);
+            let (offset, default) = match self.open.last() {
+                Some(parent) => (indent - parent.indent, parent.content - parent.indent),
+                None => (indent, 0),
+            };
+
+            let (text, id) = split_id(&text);
+            let (title, due, priority) = parse_text(text);
+            let parents = self
+                .open
+                .iter()
+                .map(|task| task.task.title().to_string())
+                .collect::<Vec<_>>();
+            // counted even when the id is written, as the writer does
+            let derived_id = self.ids.next(&parents, &title);
+            let mut task = Task::with_id(id.map_or(derived_id, str::to_string), title);
+            task.set_due(due);
+            task.set_priority(priority);
+            if text != item_text(&task) {
+                task.set_field(TEXT_FIELD, Value::from(text));
+            }
+            match check.as_str() {
+                " " => {}
+                "x" => task.complete(),
+                _ => {
+                    task.complete();
+                    task.set_field(CHECK_FIELD, Value::from(check));
+                }
+            }
+            if marker != "-" {
+                task.set_field(MARKER_FIELD, Value::from(marker.clone()));
+            }
+            if offset != default {
+                task.set_field(INDENT_FIELD, Value::from(offset));
+            }
+            self.open.push(OpenTask {
+                task,
+                indent,
+                content: indent + marker.len() + 1,
+                notes: Vec::new(),
+                after: Vec::new(),
+            });
+            self.prose = Prose::Notes;
+            return;
+        }
+
+        self.read_prose(line);
+    }
+
+    fn read_prose(&mut self, line: &str) {
+        if let Prose::Notes = self.prose {
+            let task = self.open.last_mut().expect("notes belong to a task");
+            if line.trim().is_empty() {
+                self.blanks += 1;
+                return;
+            }
+            let indent = line.len() - line.trim_start_matches(' ').len();
+            if indent >= task.content {
+                let note = match self.in_code {
+                    true => line[task.content..].to_string(),
+                    false => unescape_note(&line[task.content..], &self.item),
+                };
+                task.notes.extend(std::iter::repeat_n(String::new(), self.blanks));
+                task.notes.push(note);
+                self.blanks = 0;
+                return;
+            }
+            self.prose = Prose::After;
+        }
+        self.flush_blanks();
+        self.push_prose(line.to_string());
+    }
+
+    /// Blank lines after notes that no more notes followed.
+    fn flush_blanks(&mut self) {
+        let blanks = std::mem::take(&mut self.blanks);
+        if let Prose::Notes = self.prose {
+            if blanks > 0 {
+                self.prose = Prose::After;
+            }
+        }
+        for _ in 0..blanks {
+            self.push_prose(String::new());
+        }
+    }
+
+    fn push_prose(&mut self, line: String) {
+        match self.prose {
+            Prose::Before => self.before.push(line),
+            Prose::Intro => self.intro.push(line),
+            Prose::Notes | Prose::After => match self.open.last_mut() {
+                Some(task) => task.after.push(line),
+                None => self.intro.push(line),
+            },
+        }
+    }
+
+    /// Finishes the open tasks indented at least as far as `indent`, the
+    /// ones a new item at `indent` can't be a subtask of.
+    fn close_tasks(&mut self, indent: usize) {
+        while self.open.last().is_some_and(|task| task.indent >= indent) {
+            let open = self.open.pop().expect("checked above");
+            let mut task = open.task;
+            task.set_notes(open.notes.join("\n"));
+            if !open.after.is_empty() {
+                task.set_field(AFTER_FIELD, Value::from(open.after));
+            }
+            match self.open.last_mut() {
+                Some(parent) => parent.task.subtasks_mut().push(task),
+                None => self.tasks.push(task),
+            }
+        }
+    }
+
+    /// Ends the list read so far. Prose before the first heading waits
+    /// for the list of that heading, unless the file ends first.
+    fn finish_list(&mut self, end: bool) {
+        self.flush_blanks();
+        self.close_tasks(0);
+        let tasks = std::mem::take(&mut self.tasks);
+        let intro = std::mem::take(&mut self.intro);
+        let name = self.name.take();
+        let prose_only = end && self.lists.is_empty() && !self.before.is_empty();
+        if name.is_none() && tasks.is_empty() && intro.is_empty() && !prose_only {
+            return;
+        }
+
+        let mut list = TaskList::with_tasks(
+            name.unwrap_or_else(|| DEFAULT_LIST_NAME.to_string()),
+            tasks,
+        );
+        list.set_field(
+            HEADING_FIELD,
+            Value::from(std::mem::take(&mut self.heading_field)),
+        );
+        if let Some(newline) = self.newline {
+            list.set_field(NEWLINE_FIELD, Value::from(newline));
+        }
+        if !intro.is_empty() {
+            list.set_field(INTRO_FIELD, Value::from(intro));
+        }
+        if self.lists.is_empty() && !self.before.is_empty() {
+            list.set_field(BEFORE_FIELD, Value::from(std::mem::take(&mut self.before)));
+        }
+        self.lists.push(list);
+    }
+}
+
+/// Splits a written id off the end of an item's text.
+fn split_id(text: &str) -> (&str, Option<&str>) {
+    let Some(rest) = text.strip_suffix(ID_COMMENT_END) else {
+        return (text, None);
+    };
+    match rest.rfind(ID_COMMENT_START) {
+        Some(start) if !rest[start + ID_COMMENT_START.len()..].contains(' ') => (
+            rest[..start].strip_suffix(' ').unwrap_or(&rest[..start]),
+            Some(&rest[start + ID_COMMENT_START.len()..]),
+        ),
+        _ => (text, None),
+    }
+}
+
+/// Splits `due:` and `!priority` off the end of an item's text, and takes
+/// out the backslash [`item_text`] escapes a title with.
+fn parse_text(text: &str) -> (String, Option<Date>, Option<Priority>) {
+    let mut words = text.split(' ').collect::<Vec<_>>();
+    let mut due = None;
+    let mut priority = None;
+    while let Some(word) = words.last() {
+        match (parse_due(word), parse_priority(word)) {
+            (Some(date), _) if due.is_none() => due = Some(date),
+            (_, Some(parsed)) if priority.is_none() => priority = Some(parsed),
+            _ => break,
+        }
+        words.pop();
+    }
+
+    let mut title = words.join(" ");
+    let last = title.rfind(' ').map_or(0, |space| space + 1);
+    if title[last..].starts_with('\\') && is_token(title[last..].trim_start_matches('\\')) {
+        title.remove(last);
+    }
+    (title, due, priority)
+}
+
+fn parse_due(word: &str) -> Option<Date> {
+    word.strip_prefix("due:")
+        .and_then(|date| Date::parse(date, format_description!("[year]-[month]-[day]")).ok())
+}
+
+fn parse_priority(word: &str) -> Option<Priority> {
+    match word {
+        "!high" => Some(Priority::High),
+        "!medium" => Some(Priority::Medium),
+        "!low" => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+/// Whether `word` would be read as a due date or a priority at the end of
+/// an item.
+fn is_token(word: &str) -> bool {
+    parse_due(word).is_some() || parse_priority(word).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_list::TaskPath;
+
+    const FILE: &str = "\
+Notes for the week, not tasks.
+
+## Work
+
+Due before the release:
+
+- [ ] Ship the release due:2026-10-20 !high
+  Check the changelog first.
+
+  Then tag it.
+  * [x] Write the changelog
+- [x] Book the room
+
+Anything else goes here.
+
+```
+- [ ] not a task, just an example
+```
+
+# Home
+1. [ ] Buy milk
+";
+
+    fn titles(tasks: &[Task]) -> Vec<&str> {
+        tasks.iter().map(Task::title).collect()
+    }
+
+    #[test]
+    fn round_trip_keeps_the_file_as_it_was() {
+        let files = [
+            FILE,
+            "## Work ##\n- [ ] a\n",
+            "# Work\r\n\r\n- [ ] a\r\n  note\r\n",
+            "- [X] a\n",
+            "- [ ] a",
+            "- [ ] a !high due:2026-10-20\n",
+            "- [ ] a\n    - [ ] b\n        - [ ] c\n",
+            "1. [ ] a\n   1. [ ] b\n",
+        ];
+        for file in files {
+            assert_eq!(to_markdown(&from_markdown(file)), file);
+        }
+    }
+
+    /// Writes `tasks` as the only list and reads them back.
+    fn round_trip(tasks: Vec<Task>) -> (String, Vec<Task>) {
+        let todo = Todo::with_lists(vec![TaskList::with_tasks("Work".to_string(), tasks)]);
+        let file = to_markdown(&todo);
+        let read = from_markdown(&file);
+        (file, read.get_lists()[0].get_incomplete_tasks().clone())
+    }
+
+    #[test]
+    fn escapes_titles_ending_in_a_due_date_or_priority() {
+        let mut read = Task::new("Read !high".to_string());
+        read.set_priority(Some(Priority::Low));
+        let pay = Task::new("Pay due:2026-10-20".to_string());
+        let kept = Task::new("Keep \\!low".to_string());
+
+        let (file, tasks) = round_trip(vec![read, pay, kept]);
+        assert!(file.contains("- [ ] Read \\!high !low"));
+        assert!(file.contains("- [ ] Pay \\due:2026-10-20"));
+        assert!(file.contains("- [ ] Keep \\\\!low"));
+        assert_eq!(titles(&tasks), vec!["Read !high", "Pay due:2026-10-20", "Keep \\!low"]);
+        assert_eq!(tasks[0].priority(), Some(Priority::Low));
+        assert_eq!(tasks[1].due(), None);
+        assert_eq!(tasks[2].priority(), None);
+    }
+
+    #[test]
+    fn escapes_notes_that_would_be_read_as_items() {
+        let mut task = Task::new("a".to_string());
+        task.set_notes("- [ ] not a subtask\n  1. [x] nor this\n\\- [ ] a backslash".to_string());
+
+        let (file, tasks) = round_trip(vec![task]);
+        assert!(file.contains("  \\- [ ] not a subtask\n    \\1. [x] nor this\n  \\\\- [ ]"));
+        assert_eq!(tasks.len(), 1);
+        assert!(tasks[0].subtasks().is_empty());
+        assert_eq!(tasks[0].notes(), "- [ ] not a subtask\n  1. [x] nor this\n\\- [ ] a backslash");
+    }
+
+    #[test]
+    fn keeps_code_fences_in_notes() {
+        let mut fenced = Task::new("a".to_string());
+        fenced.set_notes("```\n- [ ] in code\n```".to_string());
+        let mut unclosed = Task::new("b".to_string());
+        unclosed.set_notes("```rust".to_string());
+        let after = Task::new("c".to_string());
+
+        let (file, tasks) = round_trip(vec![fenced, unclosed, after]);
+        assert!(file.contains("  ```\n  - [ ] in code\n  ```\n"));
+        assert!(file.contains("  \\```rust\n"));
+        assert_eq!(titles(&tasks), vec!["a", "b", "c"]);
+        assert_eq!(tasks[0].notes(), "```\n- [ ] in code\n```");
+        assert_eq!(tasks[1].notes(), "```rust");
+    }
+
+    #[test]
+    fn reads_lists_tasks_and_notes() {
+        let todo = from_markdown(FILE);
+        let lists = todo.get_lists();
+        assert_eq!(lists.iter().map(TaskList::get_name).collect::<Vec<_>>(), vec!["Work", "Home"]);
+
+        let work = &lists[0];
+        assert_eq!(titles(work.get_incomplete_tasks()), vec!["Ship the release"]);
+        assert_eq!(titles(work.get_complete_tasks()), vec!["Book the room"]);
+
+        let release = &work.get_incomplete_tasks()[0];
+        assert_eq!(release.priority(), Some(Priority::High));
+        assert_eq!(release.due().map(due::format_date), Some("2026-10-20".to_string()));
+        assert_eq!(release.notes(), "Check the changelog first.\n\nThen tag it.");
+        assert_eq!(titles(release.subtasks()), vec!["Write the changelog"]);
+        assert!(release.subtasks()[0].is_complete());
+
+        assert_eq!(titles(lists[1].get_incomplete_tasks()), vec!["Buy milk"]);
+    }
+
+    #[test]
+    fn keeps_prose_of_a_file_without_tasks() {
+        let file = "Just some thoughts.\n";
+        assert_eq!(to_markdown(&from_markdown(file)), file);
+    }
+
+    #[test]
+    fn ids_are_the_same_every_time_the_file_is_read() {
+        let ids = |todo: &Todo| {
+            todo.get_lists()
+                .iter()
+                .flat_map(|list| list.all_tasks())
+                .map(|task| task.id().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&from_markdown(FILE)), ids(&from_markdown(FILE)));
+    }
+
+    #[test]
+    fn writes_ids_that_cant_be_derived() {
+        let mut todo = from_markdown("- [ ] alpha\n- [ ] beta\n");
+        let gamma = Task::new("gamma".to_string());
+        let id = gamma.id().to_string();
+        todo.get_list_mut(0).add_task(&TaskPath::root(1), gamma);
+
+        let file = to_markdown(&todo);
+        assert_eq!(file, format!("- [ ] alpha\n- [ ] gamma <!-- id:{} -->\n- [ ] beta\n", id));
+
+        let read = from_markdown(&file);
+        assert_eq!(read.get_lists()[0].get_incomplete_tasks()[1].id(), id);
+        assert_eq!(read.get_lists()[0].get_incomplete_tasks()[1].title(), "gamma");
+        assert_eq!(to_markdown(&read), file);
+    }
+}
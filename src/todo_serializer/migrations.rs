@@ -0,0 +1,241 @@
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::task::Task;
+use crate::todo::DEFAULT_LIST_NAME;
+
+/// Version of the document layout written by this build.
+pub const CURRENT_VERSION: u64 = 2;
+
+type Migration = fn(Value) -> Result<Value, MigrationError>;
+
+/// `MIGRATIONS[n]` upgrades a version `n` document to version `n + 1`.
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1, migrate_v1_to_v2];
+
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The file was written by a newer build than this one.
+    UnsupportedVersion(u64),
+    /// The document does not have the shape its version promises.
+    Malformed { version: u64, reason: String },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MigrationError::UnsupportedVersion(version) => write!(
+                f,
+                "file uses schema v{} but this build only understands up to v{}",
+                version, CURRENT_VERSION
+            ),
+            MigrationError::Malformed { version, reason } => {
+                write!(f, "could not upgrade schema v{}: {}", version, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+/// The versioned document written to disk.
+#[derive(Serialize)]
+pub struct Envelope<'a, T: Serialize> {
+    version: u64,
+    todo: &'a T,
+}
+
+/// Wraps a todo in the versioned envelope.
+pub fn wrap<T: Serialize>(todo: &T) -> Envelope<'_, T> {
+    Envelope {
+        version: CURRENT_VERSION,
+        todo,
+    }
+}
+
+/// Unwraps a document of any known version and upgrades it to
+/// [`CURRENT_VERSION`], returning the bare todo value.
+pub fn migrate(document: Value) -> Result<Value, MigrationError> {
+    let (mut version, mut todo) = unwrap(document)?;
+
+    if version > CURRENT_VERSION {
+        return Err(MigrationError::UnsupportedVersion(version));
+    }
+
+    while version < CURRENT_VERSION {
+        todo = MIGRATIONS[version as usize](todo)?;
+        version += 1;
+    }
+
+    Ok(todo)
+}
+
+/// Documents written before the envelope existed are treated as version 0.
+fn unwrap(document: Value) -> Result<(u64, Value), MigrationError> {
+    let Value::Object(mut object) = document else {
+        return Err(malformed(0, "expected a JSON object"));
+    };
+
+    let Some(version) = object.remove("version") else {
+        return Ok((0, Value::Object(object)));
+    };
+
+    let version = version
+        .as_u64()
+        .ok_or_else(|| malformed(0, "\"version\" is not a number"))?;
+    let todo = object
+        .remove("todo")
+        .ok_or_else(|| malformed(version, "missing \"todo\" field"))?;
+
+    Ok((version, todo))
+}
+
+/// v0 stored every task as a bare title string. v1 stores task records.
+/// Some v0 files were already written with records, so those pass through.
+fn migrate_v0_to_v1(todo: Value) -> Result<Value, MigrationError> {
+    let Value::Object(mut object) = todo else {
+        return Err(malformed(0, "expected a JSON object"));
+    };
+
+    let mut upgraded = Map::new();
+    for (key, complete) in [("complete_tasks", true), ("incomplete_tasks", false)] {
+        let tasks = match object.remove(key) {
+            Some(Value::Array(tasks)) => tasks,
+            Some(_) => return Err(malformed(0, &format!("\"{}\" is not a list", key))),
+            None => Vec::new(),
+        };
+
+        let tasks = tasks
+            .into_iter()
+            .map(|task| match task {
+                Value::String(title) => {
+                    let mut task = Task::new(title);
+                    if complete {
+                        task.complete();
+                    }
+                    serde_json::to_value(task).map_err(|e| malformed(0, &e.to_string()))
+                }
+                Value::Object(_) => Ok(task),
+                _ => Err(malformed(0, &format!("unexpected entry in \"{}\"", key))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        upgraded.insert(key.to_string(), Value::Array(tasks));
+    }
+
+    Ok(Value::Object(upgraded))
+}
+
+/// v1 held a single list. v2 holds named lists, so the old tasks become
+/// the default list.
+fn migrate_v1_to_v2(todo: Value) -> Result<Value, MigrationError> {
+    let Value::Object(mut list) = todo else {
+        return Err(malformed(1, "expected a JSON object"));
+    };
+
+    list.insert("name".to_string(), Value::String(DEFAULT_LIST_NAME.to_string()));
+
+    let mut upgraded = Map::new();
+    upgraded.insert("lists".to_string(), Value::Array(vec![Value::Object(list)]));
+    upgraded.insert("active_list".to_string(), Value::from(0));
+    Ok(Value::Object(upgraded))
+}
+
+fn malformed(version: u64, reason: &str) -> MigrationError {
+    MigrationError::Malformed {
+        version,
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::todo::Todo;
+    use serde_json::json;
+
+    fn load(document: Value) -> Todo {
+        serde_json::from_value(migrate(document).unwrap()).unwrap()
+    }
+
+    fn titles(tasks: &[Task]) -> Vec<&str> {
+        tasks.iter().map(Task::title).collect()
+    }
+
+    #[test]
+    fn loads_a_v0_file_of_titles() {
+        let todo = load(json!({
+            "complete_tasks": ["File taxes"],
+            "incomplete_tasks": ["Write report", "Call mom"],
+        }));
+
+        let [list] = todo.get_lists().as_slice() else {
+            panic!("expected one list");
+        };
+        assert_eq!(list.get_name(), DEFAULT_LIST_NAME);
+        assert_eq!(titles(list.get_incomplete_tasks()), vec!["Write report", "Call mom"]);
+        assert_eq!(titles(list.get_complete_tasks()), vec!["File taxes"]);
+        assert!(list.get_complete_tasks()[0].is_complete());
+        assert!(!list.get_incomplete_tasks()[0].is_complete());
+    }
+
+    #[test]
+    fn loads_a_v0_file_already_holding_records() {
+        let record = serde_json::to_value(Task::new("Water plants".to_string())).unwrap();
+        let todo = load(json!({ "incomplete_tasks": [record, "Feed cat"] }));
+
+        let list = &todo.get_lists()[0];
+        assert_eq!(titles(list.get_incomplete_tasks()), vec!["Water plants", "Feed cat"]);
+        assert!(list.get_complete_tasks().is_empty());
+    }
+
+    #[test]
+    fn loads_a_v1_file() {
+        let todo = load(json!({
+            "version": 1,
+            "todo": {
+                "complete_tasks": [],
+                "incomplete_tasks": [{
+                    "id": "0123456789abcdef",
+                    "title": "Renew passport",
+                    "created_at": "2024-01-15T10:00:00Z",
+                    "completed_at": null,
+                }],
+            },
+        }));
+
+        let [list] = todo.get_lists().as_slice() else {
+            panic!("expected one list");
+        };
+        assert_eq!(list.get_name(), DEFAULT_LIST_NAME);
+        assert_eq!(list.get_incomplete_tasks()[0].id(), "0123456789abcdef");
+        assert_eq!(titles(list.get_incomplete_tasks()), vec!["Renew passport"]);
+    }
+
+    #[test]
+    fn leaves_a_current_file_as_it_is() {
+        let todo = json!({ "lists": [], "active_list": 0 });
+        let document = json!({ "version": CURRENT_VERSION, "todo": todo.clone() });
+
+        assert_eq!(migrate(document).unwrap(), todo);
+    }
+
+    #[test]
+    fn rejects_a_newer_version() {
+        let document = json!({ "version": CURRENT_VERSION + 1, "todo": {} });
+
+        assert!(matches!(
+            migrate(document),
+            Err(MigrationError::UnsupportedVersion(version)) if version == CURRENT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn rejects_a_versioned_file_without_todo() {
+        assert!(matches!(
+            migrate(json!({ "version": 1 })),
+            Err(MigrationError::Malformed { version: 1, .. })
+        ));
+    }
+}
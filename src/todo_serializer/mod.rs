@@ -1,13 +1,40 @@
 use crate::todo::Todo;
 use std::error::Error;
 
+mod backup;
+mod export;
 mod json_serializer;
+mod lock;
+mod markdown_serializer;
+mod migrations;
 
+pub use backup::{backups, restore, Backup};
+pub use export::export;
 pub use json_serializer::JsonSerializer;
+pub use lock::{FileLock, LockError};
+pub use markdown_serializer::MarkdownSerializer;
+pub use migrations::{MigrationError, CURRENT_VERSION};
 
 pub trait TodoSerializer {
     fn save(&self, todo: &Todo) -> Result<(), Box<dyn Error>>;
     fn load(&self) -> Result<Todo, Box<dyn Error>>;
+
+    /// Locks what the todo is saved to against other todo-term processes,
+    /// see [`FileLock::acquire`]. Serializers without a file have nothing
+    /// to lock.
+    fn lock(&self, _take_over: bool) -> Result<Option<FileLock>, LockError> {
+        Ok(None)
+    }
+}
+
+/// The serializer for the file at `path`: Markdown for `.md` and
+/// `.markdown` files, JSON for anything else.
+pub fn for_path(path: &str) -> Box<dyn TodoSerializer> {
+    if markdown_serializer::is_markdown(path) {
+        Box::new(MarkdownSerializer::new(path.to_string()))
+    } else {
+        Box::new(JsonSerializer::new(path.to_string()))
+    }
 }
 
 impl std::fmt::Debug for dyn TodoSerializer + 'static {
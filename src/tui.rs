@@ -1,12 +1,19 @@
 use crate::app::{App, AppResult};
 use crate::event::EventHandler;
 use crate::ui;
-use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+};
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
 use ratatui::backend::Backend;
 use ratatui::Terminal;
 use std::io;
 use std::panic;
+use std::sync::Once;
+
+/// The panic hook is installed by the first [`Tui::init`] only, so that
+/// resuming after a suspend does not wrap it again.
+static PANIC_HOOK: Once = Once::new();
 
 /// Representation of a terminal user interface.
 ///
@@ -31,15 +38,22 @@ impl<B: Backend> Tui<B> {
     /// It enables the raw mode and sets terminal properties.
     pub fn init(&mut self) -> AppResult<()> {
         terminal::enable_raw_mode()?;
-        crossterm::execute!(io::stderr(), EnterAlternateScreen, EnableMouseCapture)?;
+        crossterm::execute!(
+            io::stderr(),
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableBracketedPaste
+        )?;
 
         // Define a custom panic hook to reset the terminal properties.
         // This way, you won't have your terminal messed up if an unexpected error happens.
-        let panic_hook = panic::take_hook();
-        panic::set_hook(Box::new(move |panic| {
-            Self::reset().expect("failed to reset the terminal");
-            panic_hook(panic);
-        }));
+        PANIC_HOOK.call_once(|| {
+            let panic_hook = panic::take_hook();
+            panic::set_hook(Box::new(move |panic| {
+                Self::reset().expect("failed to reset the terminal");
+                panic_hook(panic);
+            }));
+        });
 
         self.terminal.hide_cursor()?;
         self.terminal.clear()?;
@@ -61,7 +75,25 @@ impl<B: Backend> Tui<B> {
     /// the terminal properties if unexpected errors occur.
     fn reset() -> AppResult<()> {
         terminal::disable_raw_mode()?;
-        crossterm::execute!(io::stderr(), LeaveAlternateScreen, DisableMouseCapture)?;
+        crossterm::execute!(
+            io::stderr(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            DisableBracketedPaste
+        )?;
+        Ok(())
+    }
+
+    /// Hands the terminal over to another program, such as `$EDITOR`.
+    pub fn suspend(&mut self) -> AppResult<()> {
+        self.events.pause();
+        self.exit()
+    }
+
+    /// Takes the terminal back after [`suspend`](Tui::suspend).
+    pub fn resume(&mut self) -> AppResult<()> {
+        self.init()?;
+        self.events.resume();
         Ok(())
     }
 
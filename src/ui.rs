@@ -8,15 +8,35 @@ use ratatui::{
         line::NORMAL,
     },
     widgets::{
-        calendar::CalendarEventStore, calendar::Monthly, Block, BorderType, Borders, Clear, List,
-        ListItem, Padding, Paragraph, HighlightSpacing,
+        block::{Position, Title}, calendar::CalendarEventStore, calendar::Monthly, Block, BorderType, Borders, Clear, List,
+        ListItem, Padding, Paragraph, HighlightSpacing, Wrap,
     },
     Frame,
 };
 
-use crate::app::{App, AppMode};
+use crate::app::{App, AppMode, Focus, BACKUP_TIME_FORMAT};
+use crate::keymap::KeymapMode;
 
-use time::OffsetDateTime;
+use crate::data_file;
+use crate::due;
+use crate::search::Search;
+use crate::tags;
+use crate::task::{Priority, Task};
+
+use std::path::Path;
+
+use time::Date;
+use unicode_segmentation::UnicodeSegmentation;
+
+const DUE_COLOR: Color = Color::Rgb(228, 200, 100);
+const OVERDUE_COLOR: Color = Color::Rgb(228, 100, 100);
+const TAG_COLOR: Color = Color::Rgb(100, 200, 228);
+const CONTEXT_COLOR: Color = Color::Rgb(180, 140, 228);
+const MATCH_COLOR: Color = Color::Rgb(228, 200, 100);
+const SELECTION_COLOR: Color = Color::Rgb(10, 50, 64);
+
+/// The month and weekday headers, six weeks and the borders.
+const CALENDAR_HEIGHT: u16 = 10;
 
 pub fn render(app: &mut App, frame: &mut Frame) {
     let areas = Layout::default()
@@ -34,32 +54,58 @@ pub fn render(app: &mut App, frame: &mut Frame) {
 
     let content_area = main_areas[0];
     let sidebar_area = main_areas[1];
-    let help_popup_area = centered_rect_length(32, 20, content_area);
     let footer_area = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Min(0), Constraint::Length(1)])
         .split(content_area)[1];
 
-    draw_header(frame, header_area);
-    draw_calendar(frame, sidebar_area);
+    // the notes pane takes what the calendar leaves, if that is enough
+    let (calendar_area, notes_area) = if sidebar_area.height >= CALENDAR_HEIGHT + 3 {
+        let sidebar_areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(CALENDAR_HEIGHT), Constraint::Min(0)])
+            .split(sidebar_area);
+        (sidebar_areas[0], Some(sidebar_areas[1]))
+    } else {
+        (sidebar_area, None)
+    };
+
+    app.get_hit_map_mut().set_list(content_area);
+    app.get_hit_map_mut().set_calendar(calendar_area);
+
+    draw_header(frame, app, header_area);
+    draw_calendar(frame, app, calendar_area, notes_area.is_some());
+    if let Some(notes_area) = notes_area {
+        draw_notes(frame, app, notes_area);
+    }
 
     if app.is_todo_empty() {
-        draw_empty_content(frame, content_area);
+        draw_empty_content(frame, app, content_area);
     } else {
         draw_list(frame, app, content_area);
     }
 
     draw_footer(frame, app, footer_area);
 
+    if app.get_mode() == AppMode::TagPicker {
+        draw_tag_picker(frame, app, content_area);
+    }
+
+    if app.get_mode() == AppMode::Recover {
+        draw_backup_picker(frame, app, content_area);
+    }
+
     if app.is_help_visible() {
-        draw_help_popup(frame, help_popup_area);
+        draw_help_popup(frame, app, content_area);
     }
 }
 
 fn draw_footer(f: &mut Frame, app: &mut App, area: Rect) {
+    // the action only takes the room it needs so prompts can use the rest
+    let action_width = Line::from(app.get_action()).width() as u16;
     let footer_areas = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Min(10), Constraint::Min(10)])
+        .constraints([Constraint::Min(10), Constraint::Length(action_width)])
         .split(area);
 
     let footer_left = footer_areas[0];
@@ -69,7 +115,23 @@ fn draw_footer(f: &mut Frame, app: &mut App, area: Rect) {
         .alignment(Alignment::Right)
         .style(Style::default());
 
-    let mode_display = Paragraph::new(app.get_mode().to_string())
+    let mode_text = match app.get_mode() {
+        AppMode::Prompt(_) => format!("{}: {}█", app.get_prompt_label(), app.get_prompt()),
+        AppMode::Confirm => app.get_confirm_question().unwrap_or_default(),
+        AppMode::Command => format!(":{}█", app.get_command_line()),
+        mode => {
+            let mut text = mode.to_string();
+            if let Some(rows) = app.get_selection() {
+                text.push_str(&format!(" ({})", rows.count()));
+            }
+            if !app.get_pending_keys().is_empty() {
+                text.push_str(&format!("  {}", app.get_pending_keys()));
+            }
+            text
+        }
+    };
+
+    let mode_display = Paragraph::new(mode_text)
         .alignment(Alignment::Left)
         .block(
             Block::default()
@@ -81,21 +143,18 @@ fn draw_footer(f: &mut Frame, app: &mut App, area: Rect) {
     f.render_widget(action_display, footer_right);
 }
 
-fn draw_help_popup(f: &mut Frame, area: Rect) {
-    let help_text = vec![
-        "Toggle help    - h",
-        "Quit           - q, Ctrl-C,",
-        "                 Esc",
-        "Exit insert    - Ctrl-C, End",
-        "Navigate       - j, k, g, G",
-        "Add task       - o, O",
-        "Toggle task    - x",
-        "Delete task    - d",
-        "Change task    - c",
-        "Append to task - a",
-        "Yank task      - y",
-        "Paste task     - p, P",
-    ];
+/// The help lists the bindings of the keymap, so it follows the config.
+fn draw_help_popup(f: &mut Frame, app: &App, area: Rect) {
+    let keymap = app.get_keymap();
+    let help_text = keymap
+        .help(KeymapMode::Visual)
+        .into_iter()
+        .chain(keymap.help(KeymapMode::Insert))
+        .map(|(help, keys)| format!("{:<15}- {}", help, keys))
+        .collect::<Vec<_>>();
+
+    let width = help_text.iter().map(|line| line.chars().count()).max().unwrap_or(0) as u16 + 4;
+    let area = centered_rect_length(width, help_text.len() as u16 + 4, area);
 
     let help = Paragraph::new(help_text.join("\n")).block(
         Block::default()
@@ -111,6 +170,78 @@ fn draw_help_popup(f: &mut Frame, area: Rect) {
     f.render_widget(help, area);
 }
 
+/// Lists the tags of the open list. Tags in the filter are checked.
+fn draw_tag_picker(f: &mut Frame, app: &mut App, area: Rect) {
+    let tags = app.get_tag_counts();
+    let filter = app.get_view().get_filter();
+    let items = tags
+        .iter()
+        .map(|(tag, count)| {
+            let check = if filter.has_tag(tag) { "[x] " } else { "[ ] " };
+            ListItem::new(Line::from(vec![
+                check.fg(Color::Rgb(200, 200, 200)),
+                tag.clone().fg(tag_color(tag)),
+                format!(" ({})", count).fg(Color::DarkGray),
+            ]))
+        })
+        .collect::<Vec<_>>();
+
+    let height = (items.len() as u16 + 2).min(area.height);
+    let popup_area = centered_rect_length(28, height, area);
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Double)
+                .border_style(Style::default().fg(Color::DarkGray))
+                .title("Tags")
+                .title_style(Style::default().fg(Color::Rgb(100, 200, 228))),
+        )
+        .highlight_style(Style::default().bg(Color::Rgb(60, 60, 60)))
+        .highlight_symbol("> ")
+        .highlight_spacing(HighlightSpacing::Always);
+
+    f.render_widget(Clear, popup_area);
+    f.render_stateful_widget(list, popup_area, app.get_tag_picker_state());
+}
+
+/// Lists the backups of the todo file, newest first.
+fn draw_backup_picker(f: &mut Frame, app: &mut App, area: Rect) {
+    let items = app
+        .get_backups()
+        .iter()
+        .map(|(backup, count)| {
+            let taken = backup.taken.format(BACKUP_TIME_FORMAT).unwrap_or_default();
+            let count = match count {
+                Some(1) => " (1 task)".fg(Color::DarkGray),
+                Some(count) => format!(" ({} tasks)", count).fg(Color::DarkGray),
+                None => " (unreadable)".fg(OVERDUE_COLOR),
+            };
+            ListItem::new(Line::from(vec![taken.fg(Color::Rgb(200, 200, 200)), count]))
+        })
+        .collect::<Vec<_>>();
+
+    let height = (items.len() as u16 + 2).min(area.height);
+    let popup_area = centered_rect_length(44, height, area);
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Double)
+                .border_style(Style::default().fg(Color::DarkGray))
+                .title("Backups")
+                .title_style(Style::default().fg(Color::Rgb(100, 200, 228))),
+        )
+        .highlight_style(Style::default().bg(Color::Rgb(60, 60, 60)))
+        .highlight_symbol("> ")
+        .highlight_spacing(HighlightSpacing::Always);
+
+    f.render_widget(Clear, popup_area);
+    f.render_stateful_widget(list, popup_area, app.get_backup_picker_state());
+}
+
 fn centered_rect_length(width: u16, height: u16, r: Rect) -> Rect {
     if r.width < width || r.height < height {
         return r;
@@ -136,22 +267,71 @@ fn centered_rect_length(width: u16, height: u16, r: Rect) -> Rect {
         .split(popup_layout[1])[1]
 }
 
-fn draw_calendar(f: &mut Frame, area: Rect) {
-    let date = OffsetDateTime::now_utc().date();
-    let calendar = Monthly::new(
-        date,
-        CalendarEventStore::today(Style::new().fg(Color::Rgb(100, 200, 228))),
-    )
-    .block(
-        Block::default()
+fn draw_calendar(f: &mut Frame, app: &mut App, area: Rect, above_notes: bool) {
+    let date = due::today();
+
+    let mut events = CalendarEventStore::default();
+    for task in app.all_tasks().into_iter().filter(|task| !task.is_complete()) {
+        if let Some(due) = task.due() {
+            let style = if due < date {
+                Style::new().fg(OVERDUE_COLOR).add_modifier(Modifier::BOLD)
+            } else {
+                Style::new().fg(DUE_COLOR).add_modifier(Modifier::BOLD)
+            };
+            events.add(due, style);
+        }
+    }
+    let today_style = match events.0.get(&date) {
+        Some(_) => Style::new()
+            .fg(Color::Rgb(100, 200, 228))
+            .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+        None => Style::new().fg(Color::Rgb(100, 200, 228)),
+    };
+    events.add(date, today_style);
+
+    if let Some(filter_date) = app.get_view().get_filter().get_due() {
+        let style = events.0.get(&filter_date).copied().unwrap_or_default();
+        events.add(filter_date, style.add_modifier(Modifier::UNDERLINED));
+    }
+
+    let focused = app.get_focus() == Focus::Calendar;
+    let cursor = app.get_calendar_date();
+    if focused {
+        let style = events.0.get(&cursor).copied().unwrap_or_default();
+        events.add(cursor, style.add_modifier(Modifier::REVERSED));
+    }
+
+    let border_color = if focused {
+        Color::Rgb(100, 200, 228)
+    } else {
+        Color::DarkGray
+    };
+
+    let mut block = Block::default()
+        .border_set(Set {
+            top_left: NORMAL.horizontal_down,
+            bottom_left: NORMAL.horizontal_up,
+            ..ROUNDED
+        })
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(border_color));
+    if above_notes {
+        block = block
             .border_set(Set {
                 top_left: NORMAL.horizontal_down,
-                bottom_left: NORMAL.horizontal_up,
+                bottom_left: NORMAL.vertical_right,
+                bottom_right: NORMAL.vertical_left,
                 ..ROUNDED
             })
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::DarkGray)),
-    )
+            .title(
+                Title::from(" Notes ".fg(Color::DarkGray))
+                    .position(Position::Bottom),
+            );
+    }
+    add_calendar_days(app, cursor, block.inner(area));
+
+    let calendar = Monthly::new(cursor, events)
+    .block(block)
     .show_surrounding(Style::new().fg(Color::DarkGray))
     .show_month_header(Style::new().add_modifier(Modifier::BOLD))
     .show_weekdays_header(Style::new().add_modifier(Modifier::ITALIC));
@@ -159,110 +339,309 @@ fn draw_calendar(f: &mut Frame, area: Rect) {
     f.render_widget(calendar, area);
 }
 
-fn draw_header(f: &mut Frame, area: Rect) {
-    let line: Line = vec![
+/// The notes of the selected task under the calendar.
+fn draw_notes(f: &mut Frame, app: &App, area: Rect) {
+    let block = Block::default()
+        .border_set(Set {
+            bottom_left: NORMAL.horizontal_up,
+            ..ROUNDED
+        })
+        .borders(Borders::LEFT | Borders::RIGHT | Borders::BOTTOM)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .padding(Padding::horizontal(1));
+
+    let notes = match app.get_selected_task().map(Task::notes) {
+        Some(notes) if !notes.is_empty() => Paragraph::new(notes.to_string()),
+        _ => Paragraph::new("No notes".fg(Color::DarkGray).italic()),
+    };
+
+    f.render_widget(notes.wrap(Wrap { trim: false }).block(block), area);
+}
+
+/// Records where [`Monthly`] draws each day of the month around `date`: a
+/// line each for the month and the weekdays, then a week per line with days
+/// two cells wide after a one cell gutter.
+fn add_calendar_days(app: &mut App, date: Date, inner: Rect) {
+    let first_of_month = date.replace_day(1).expect("every month has a first day");
+    let mut day = first_of_month
+        - time::Duration::days(first_of_month.weekday().number_days_from_sunday().into());
+    let mut y = inner.y + 2;
+    while day.month() != first_of_month.month().next() && y < inner.bottom() {
+        for column in 0..7 {
+            let area = Rect::new(inner.x + 3 * column, y, 3, 1).intersection(inner);
+            app.get_hit_map_mut().add_day(area, day);
+            day += time::Duration::DAY;
+        }
+        y += 1;
+    }
+}
+
+fn draw_header(f: &mut Frame, app: &App, area: Rect) {
+    let mut spans = vec![
         " ~todo-term🍰 "
             .fg(Color::White)
             .bg(Color::Rgb(20, 100, 128)),
-        " @ D:/development/todo-term/target/release/todo.json".into(),
-    ]
-    .into();
+        format!(" {} ", app.get_active_list_name())
+            .fg(Color::White)
+            .bg(Color::Rgb(15, 75, 96)),
+    ];
+    if app.is_read_only() {
+        spans.push(" read-only ".fg(Color::Black).bg(DUE_COLOR));
+    }
+    spans.push(format!(" @ {}", data_file::display(Path::new(app.get_file_path()))).into());
+    let line = Line::from(spans);
 
     let header = Paragraph::new(line).style(Style::default().bg(Color::Rgb(10, 50, 64)));
 
     f.render_widget(header, area);
 }
 
+fn due_label(task: &Task, today: Date) -> Option<Span<'static>> {
+    let due = task.due()?;
+    let color = match due.cmp(&today) {
+        std::cmp::Ordering::Less => OVERDUE_COLOR,
+        std::cmp::Ordering::Equal => DUE_COLOR,
+        std::cmp::Ordering::Greater => Color::DarkGray,
+    };
+    Some(format!(" ({})", due::relative_label(due, today)).fg(color))
+}
+
+fn tag_color(tag: &str) -> Color {
+    if tag.starts_with('@') {
+        CONTEXT_COLOR
+    } else {
+        TAG_COLOR
+    }
+}
+
+/// The title split into spans. Tags are colored unless `dim` is set and
+/// search matches are highlighted.
+fn title_spans(
+    title: &str,
+    style: Style,
+    dim: bool,
+    search: &Search,
+    cursor: Option<usize>,
+) -> Vec<Span<'static>> {
+    let tag_ranges = if dim { Vec::new() } else { tags::tag_ranges(title) };
+    let match_ranges = search.find(title);
+    // the character under the cursor
+    let cursor_range = cursor.and_then(|cursor| {
+        let grapheme = title[cursor..].graphemes(true).next()?;
+        Some(cursor..cursor + grapheme.len())
+    });
+
+    // cut the title wherever a tag, match or the cursor starts or ends
+    let mut cuts = vec![0, title.len()];
+    for range in tag_ranges.iter().chain(match_ranges.iter()).chain(cursor_range.iter()) {
+        cuts.push(range.start);
+        cuts.push(range.end);
+    }
+    cuts.sort_unstable();
+    cuts.dedup();
+
+    let mut spans = Vec::new();
+    for pair in cuts.windows(2) {
+        let (start, end) = (pair[0], pair[1]);
+        let mut segment_style = style;
+        if let Some(range) = tag_ranges.iter().find(|r| r.start <= start && end <= r.end) {
+            segment_style = segment_style.fg(tag_color(&title[range.clone()]));
+        }
+        if match_ranges.iter().any(|r| r.start <= start && end <= r.end) {
+            segment_style = segment_style.fg(Color::Black).bg(MATCH_COLOR);
+        }
+        if cursor_range.as_ref().is_some_and(|r| r.start <= start && end <= r.end) {
+            segment_style = segment_style.fg(Color::Black).bg(Color::White);
+        }
+        spans.push(Span::styled(title[start..end].to_string(), segment_style));
+    }
+    if spans.is_empty() {
+        spans.push(Span::styled(String::new(), style));
+    }
+    spans
+}
+
+/// Indentation for the task's depth plus its fold marker.
+fn tree_prefix(task: &Task, depth: usize) -> Span<'static> {
+    let marker = match (task.subtasks().is_empty(), task.is_folded()) {
+        (true, _) => "  ",
+        (false, true) => "▸ ",
+        (false, false) => "▾ ",
+    };
+    format!("{}{}", "  ".repeat(depth), marker).fg(Color::DarkGray)
+}
+
+fn progress_label(task: &Task) -> Option<Span<'static>> {
+    let (done, total) = task.progress();
+    if total == 0 {
+        return None;
+    }
+    Some(format!(" ({}/{})", done, total).fg(Color::DarkGray))
+}
+
+fn priority_marker(task: &Task) -> Span<'static> {
+    match task.priority() {
+        Some(Priority::High) => "▲ ".fg(Color::Rgb(228, 100, 100)),
+        Some(Priority::Medium) => "■ ".fg(Color::Rgb(228, 200, 100)),
+        Some(Priority::Low) => "▼ ".fg(Color::Rgb(100, 150, 228)),
+        None => "  ".into(),
+    }
+}
+
 fn draw_list(f: &mut Frame, app: &mut App, area: Rect) {
     let selected = app.get_todo_list_state().selected().unwrap_or(usize::MAX);
+    let selection = app.get_selection();
     let app_mode = app.get_mode();
+    let today = due::today();
 
-    let incomplete_tasks = app
-        .get_incomplete_tasks()
+    let tasks = app
+        .get_view()
+        .rows()
         .iter()
         .enumerate()
-        .map(|(index, task)| {
-            let bullet = "[ ] ".fg(Color::Rgb(200, 200, 200));
-            let line = match (index == selected, app_mode) {
-                // selected and insert mode
-                (true, AppMode::Insert) => vec![
-                    bullet,
-                    task.clone().fg(Color::White).bg(Color::Rgb(60, 60, 60)),
-                    "█".fg(Color::White),
-                    " ".repeat(area.width as usize).bg(Color::Rgb(60, 60, 60)),
-                ],
-                // selected
-                (true, _) => vec![
-                    bullet.fg(Color::White),
-                    task.clone().fg(Color::White).bg(Color::Rgb(60, 60, 60)),
-                ],
-                // not selected
-                _ => vec![bullet, task.clone().fg(Color::Rgb(200, 200, 200))],
+        .map(|(row, path)| {
+            let task = app.get_task(path);
+            let indent = tree_prefix(task, path.depth());
+            let (bullet, color) = if task.is_complete() {
+                ("[x] ".fg(Color::DarkGray), Color::DarkGray)
+            } else {
+                ("[ ] ".fg(Color::Rgb(200, 200, 200)), Color::Rgb(200, 200, 200))
             };
-            ListItem::new(Line::from(line))
-        })
-        .collect::<Vec<_>>();
-
-    let incomplete_tasks_len = incomplete_tasks.len();
-
-    let complete_tasks = app
-        .get_complete_tasks()
-        .iter()
-        .enumerate()
-        .map(|(index, task)| {
-            let bullet = "[x] ".fg(Color::DarkGray);
-            let line = match (index + incomplete_tasks_len == selected, app_mode) {
+            let selected_style = Style::default().fg(Color::White).bg(Color::Rgb(60, 60, 60));
+            let dim = task.is_complete();
+            let mut line = match (row == selected, app_mode) {
                 // selected and insert mode
-                (true, AppMode::Insert) => vec![
-                    bullet,
-                    task.clone().fg(Color::White).bg(Color::Rgb(60, 60, 60)),
-                    "█".fg(Color::White),
-                    " ".repeat(area.width as usize).bg(Color::Rgb(60, 60, 60)),
-                ],
+                (true, AppMode::Insert) => {
+                    let editor = app.get_line_editor();
+                    let cursor = editor.get_cursor();
+                    let mut line = vec![indent, bullet, priority_marker(task)];
+                    line.extend(title_spans(editor.get_text(), selected_style, dim, app.get_search(), Some(cursor)));
+                    // past the end the cursor is a block of its own
+                    if cursor == editor.get_text().len() {
+                        line.push("█".fg(Color::White));
+                    }
+                    line.push(" ".repeat(area.width as usize).bg(Color::Rgb(60, 60, 60)));
+                    line
+                }
                 // selected
-                (true, _) => vec![
-                    bullet.fg(Color::White),
-                    task.clone().fg(Color::White).bg(Color::Rgb(60, 60, 60)),
-                ],
+                (true, _) => {
+                    let mut line = vec![indent, bullet.fg(Color::White), priority_marker(task)];
+                    line.extend(title_spans(task.title(), selected_style, dim, app.get_search(), None));
+                    line
+                }
                 // not selected
-                _ => vec![bullet, task.clone().fg(Color::DarkGray)],
+                _ => {
+                    let mut line = vec![indent, bullet, priority_marker(task)];
+                    line.extend(title_spans(task.title(), Style::default().fg(color), dim, app.get_search(), None));
+                    line
+                }
             };
-            ListItem::new(Line::from(line))
+            // the insert mode line is padded to the full width
+            let editing = row == selected && app_mode == AppMode::Insert;
+            if !editing {
+                line.extend(progress_label(task));
+                if !task.notes().is_empty() {
+                    line.push(" ≡".fg(Color::DarkGray));
+                }
+                if !task.is_complete() {
+                    line.extend(due_label(task, today));
+                }
+            }
+            let item = ListItem::new(Line::from(line));
+            match &selection {
+                Some(rows) if rows.contains(&row) => item.bg(SELECTION_COLOR),
+                _ => item,
+            }
         })
         .collect::<Vec<_>>();
 
-    let all_tasks = incomplete_tasks
+    let complete_tasks_len = app
+        .get_view()
+        .rows()
         .iter()
-        .chain(complete_tasks.iter())
-        .cloned()
-        .collect::<Vec<_>>();
-
-    let all_tasks_len = all_tasks.len();
+        .filter(|path| app.get_task(path).is_complete())
+        .count();
+    let all_tasks_len = tasks.len();
+
+    let filter = app.get_view().get_filter();
+    let mut title = app.get_active_list_name().to_string();
+    if let Some(date) = filter.get_due() {
+        title.push_str(&format!(" due {}", due::format_date(date)));
+    }
+    for tag in filter.get_tags() {
+        title.push_str(&format!(" {}", tag));
+    }
+    let title = format!("{} ({}/{})", title, complete_tasks_len, all_tasks_len);
+    let title = if app.is_sorted_by_priority() {
+        format!("{} by priority", title)
+    } else {
+        title
+    };
 
     let highlight_symbol = match app_mode {
         AppMode::Insert => ">>",
         _ => "> ",
     };
 
-    let list = List::new(all_tasks)
-        .block(
-            Block::default()
-                .borders(Borders::LEFT | Borders::TOP | Borders::BOTTOM)
-                .border_type(BorderType::Rounded)
-                .border_style(Style::default().fg(Color::DarkGray))
-                .title(format!(
-                    "Tasks ({}/{})",
-                    complete_tasks.len(),
-                    all_tasks_len
-                ))
-                .fg(Color::Rgb(100, 200, 228))
-                .title_style(Style::default().fg(Color::White))
-                .padding(Padding::horizontal(1)),
-        )
+    let block = Block::default()
+        .borders(Borders::LEFT | Borders::TOP | Borders::BOTTOM)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(title)
+        .title(list_tabs(app))
+        .fg(Color::Rgb(100, 200, 228))
+        .title_style(Style::default().fg(Color::White))
+        .padding(Padding::horizontal(1));
+    let inner = block.inner(area);
+
+    let list = List::new(tasks)
+        .block(block)
         .highlight_symbol(highlight_symbol)
         .highlight_spacing(HighlightSpacing::Always);
 
-    f.render_stateful_widget(list, area, &mut app.get_todo_list_state());
+    f.render_stateful_widget(list, area, app.get_todo_list_state());
+    add_list_rows(app, inner, Line::from(highlight_symbol).width() as u16);
+}
+
+/// Records where each visible row and its bullet ended up, for the mouse.
+fn add_list_rows(app: &mut App, inner: Rect, symbol_width: u16) {
+    let offset = app.get_todo_list_state().offset();
+    let rows = app
+        .get_view()
+        .rows()
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(inner.height as usize)
+        .map(|(row, path)| {
+            let indent = tree_prefix(app.get_task(path), path.depth()).width() as u16;
+            (row, indent)
+        })
+        .collect::<Vec<_>>();
+
+    for (line, (row, indent)) in rows.into_iter().enumerate() {
+        let area = Rect::new(inner.x, inner.y + line as u16, inner.width, 1);
+        let bullet = Rect::new(inner.x + symbol_width + indent, area.y, 3, 1).intersection(area);
+        app.get_hit_map_mut().add_row(row, area, bullet);
+    }
+}
+
+/// The names of all lists for the top border, the open one highlighted.
+fn list_tabs(app: &App) -> Title<'static> {
+    let active = app.get_active_list_index();
+    let mut spans = Vec::new();
+    for (index, name) in app.get_list_names().into_iter().enumerate() {
+        if index > 0 {
+            spans.push("│".fg(Color::DarkGray));
+        }
+        let tab = format!(" {} ", name);
+        spans.push(if index == active {
+            tab.fg(Color::White).bg(Color::Rgb(20, 100, 128))
+        } else {
+            tab.fg(Color::DarkGray)
+        });
+    }
+    Title::from(Line::from(spans)).alignment(Alignment::Right)
 }
 
 // create a list of faces (3 characers wide)
@@ -285,8 +664,13 @@ fn get_random_face() -> String {
     FACES[index].to_string()
 }
 
-fn draw_empty_content(f: &mut Frame, area: Rect) {
-    let text = vec![
+fn draw_empty_content(f: &mut Frame, app: &App, area: Rect) {
+    if let Some(error) = app.get_load_error() {
+        draw_load_error(f, error, area);
+        return;
+    }
+
+    let text = [
         "No tasks for today! 🎉",
         "",
         "Add one by pressing 'o'.",
@@ -304,7 +688,8 @@ fn draw_empty_content(f: &mut Frame, area: Rect) {
                 .borders(Borders::LEFT | Borders::TOP | Borders::BOTTOM)
                 .border_type(BorderType::Rounded)
                 .border_style(Style::default().fg(Color::DarkGray))
-                .title(format!("Tasks {}", get_random_face()))
+                .title(format!("{} {}", app.get_active_list_name(), get_random_face()))
+                .title(list_tabs(app))
                 .title_style(Style::default().fg(Color::White))
                 .padding(Padding::uniform(1)),
         )
@@ -312,3 +697,30 @@ fn draw_empty_content(f: &mut Frame, area: Rect) {
 
     f.render_widget(paragraph, area);
 }
+
+fn draw_load_error(f: &mut Frame, error: &str, area: Rect) {
+    let text = [
+        "Could not load your tasks.",
+        "",
+        error,
+        "",
+        "Saving is disabled so the file is left untouched.",
+        "Fix or move the file and restart, or restore a backup with :recover.",
+    ];
+
+    let paragraph = Paragraph::new(text.join("\n"))
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: false })
+        .block(
+            Block::default()
+                .borders(Borders::LEFT | Borders::TOP | Borders::BOTTOM)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(Color::DarkGray))
+                .title("Tasks (x_x)")
+                .title_style(Style::default().fg(Color::White))
+                .padding(Padding::uniform(1)),
+        )
+        .style(Style::default().fg(Color::Red));
+
+    f.render_widget(paragraph, area);
+}
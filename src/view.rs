@@ -0,0 +1,133 @@
+use time::Date;
+
+use crate::task::Task;
+use crate::task_list::{TaskList, TaskPath};
+
+/// Which tasks the list shows.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Filter {
+    due: Option<Date>,
+    /// A task must carry all of these to be shown.
+    tags: Vec<String>,
+}
+
+impl Filter {
+    pub fn get_due(&self) -> Option<Date> {
+        self.due
+    }
+
+    pub fn set_due(&mut self, due: Option<Date>) {
+        self.due = due;
+    }
+
+    pub fn get_tags(&self) -> &Vec<String> {
+        &self.tags
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Adds the tag to the filter, or removes it if it is already there.
+    pub fn toggle_tag(&mut self, tag: &str) {
+        match self.tags.iter().position(|t| t == tag) {
+            Some(index) => {
+                self.tags.remove(index);
+            }
+            None => self.tags.push(tag.to_string()),
+        }
+    }
+
+    pub fn clear_tags(&mut self) {
+        self.tags.clear();
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.due.is_some() || !self.tags.is_empty()
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        let due_matches = match self.due {
+            Some(due) => task.due() == Some(due),
+            None => true,
+        };
+        due_matches && self.tags.iter().all(|tag| task.has_tag(tag))
+    }
+}
+
+/// Maps the rows of the rendered list to task paths in a [`TaskList`].
+///
+/// The list state selects rows; anything that touches the todo goes through
+/// [`task_path`](TodoView::task_path) first.
+#[derive(Debug, Default)]
+pub struct TodoView {
+    rows: Vec<TaskPath>,
+    filter: Filter,
+}
+
+impl TodoView {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rebuilds the rows. Must be called after every change to the list, the
+    /// filter, or which list is open.
+    ///
+    /// Top-level tasks come in [display order](TaskList::display_order).
+    /// Subtasks of folded tasks are skipped. A task that does not match the
+    /// filter is still shown when one of its subtasks does, for context.
+    pub fn refresh(&mut self, list: &TaskList) {
+        self.rows.clear();
+        for index in list.display_order() {
+            let path = TaskPath::root(index);
+            collect_rows(&self.filter, list.get_task(&path), path, &mut self.rows);
+        }
+    }
+
+    pub fn get_filter(&self) -> &Filter {
+        &self.filter
+    }
+
+    pub fn get_filter_mut(&mut self) -> &mut Filter {
+        &mut self.filter
+    }
+
+    pub fn rows(&self) -> &[TaskPath] {
+        &self.rows
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn task_path(&self, row: usize) -> Option<&TaskPath> {
+        self.rows.get(row)
+    }
+
+    pub fn row_of(&self, path: &TaskPath) -> Option<usize> {
+        self.rows.iter().position(|p| p == path)
+    }
+}
+
+/// Pushes the rows for `task` and its subtasks. Returns whether anything in
+/// the subtree matched.
+fn collect_rows(filter: &Filter, task: &Task, path: TaskPath, rows: &mut Vec<TaskPath>) -> bool {
+    let start = rows.len();
+    rows.push(path.clone());
+
+    let mut matched = filter.matches(task);
+    for (index, subtask) in task.subtasks().iter().enumerate() {
+        matched |= collect_rows(filter, subtask, path.child(index), rows);
+    }
+
+    if !matched {
+        rows.truncate(start);
+    } else if task.is_folded() {
+        rows.truncate(start + 1);
+    }
+    matched
+}